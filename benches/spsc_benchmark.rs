@@ -508,6 +508,37 @@ fn benchmark_async(c: &mut Criterion) {
         NonZeroUsize::new(65_536).unwrap(),
     ];
 
+    // both ends in one thread never wait, so this only measures the cost of checking whether the
+    // other end is sleeping, which every operation pays once `async` is enabled
+    let mut group = make_group(c, "uncontended");
+
+    group.bench_function("sync", |b| {
+        b.iter_custom(|iter| {
+            let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(1024).unwrap());
+
+            let start = Instant::now();
+            for i in 0..iter as usize {
+                tx.send(black_box(i));
+                black_box(rx.recv());
+            }
+            start.elapsed()
+        });
+    });
+
+    group.bench_function("async", |b| {
+        b.to_async(FuturesExecutor).iter_custom(|iter| async move {
+            let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(1024).unwrap());
+
+            let start = Instant::now();
+            for i in 0..iter as usize {
+                tx.send_async(black_box(i)).await;
+                black_box(rx.recv_async().await);
+            }
+            start.elapsed()
+        });
+    });
+    drop(group);
+
     let mut group = make_group(c, "async_roundtrip_latency");
 
     for size in SIZES {
@@ -0,0 +1,193 @@
+//! Allocators for the memory backing the queues.
+//!
+//! Every queue is a single allocation made through an [`Allocator`], which defaults to [`Global`].
+//! The `channel_in` constructors of each flavor take any other allocator, e.g. to place queues in
+//! huge-page arenas, NUMA-local pools or static regions on embedded targets.
+//!
+//! On stable, [`Allocator`] is a trait of this crate mirroring the required methods of
+//! [`core::alloc::Allocator`]. With the `allocator_api` feature, which needs a nightly compiler, it
+//! is `core::alloc::Allocator` itself, so any allocator of the ecosystem can be used directly.
+
+use core::{alloc::Layout, ptr::NonNull};
+
+#[cfg(feature = "allocator_api")]
+pub use core::alloc::{AllocError, Allocator};
+
+/// The error returned by an [`Allocator`] that failed to allocate memory.
+#[cfg(not(feature = "allocator_api"))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AllocError;
+
+#[cfg(not(feature = "allocator_api"))]
+impl core::fmt::Display for AllocError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+#[cfg(not(feature = "allocator_api"))]
+impl core::error::Error for AllocError {}
+
+/// An allocator for the memory backing the queues.
+///
+/// This mirrors the required methods of [`core::alloc::Allocator`], which is used instead when the
+/// `allocator_api` feature is enabled.
+///
+/// # Safety
+///
+/// Memory blocks returned by [`Allocator::allocate`] must stay valid until they are passed to
+/// [`Allocator::deallocate`] of this allocator or any of its clones, and moving or cloning the
+/// allocator must not invalidate them.
+#[cfg(not(feature = "allocator_api"))]
+pub unsafe trait Allocator {
+    /// Allocates a block of memory fitting `layout`, which may be larger than requested.
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError>;
+
+    /// Deallocates the memory at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by this allocator or one of its clones with `layout`.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+}
+
+#[cfg(not(feature = "allocator_api"))]
+unsafe impl<A: Allocator + ?Sized> Allocator for &A {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        (**self).allocate(layout)
+    }
+
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { (**self).deallocate(ptr, layout) }
+    }
+}
+
+/// The global memory allocator, used by all the `channel` constructors.
+///
+/// Without the `alloc` feature there is no global allocator, and it always fails to allocate.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Global;
+
+#[cfg(feature = "alloc")]
+unsafe impl Allocator for Global {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        debug_assert!(layout.size() > 0);
+
+        // SAFETY: all the queues have a header, so the layout is never zero sized
+        let ptr = NonNull::new(unsafe { crate::alloc::alloc(layout) }).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { crate::alloc::dealloc(ptr.as_ptr(), layout) }
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+unsafe impl Allocator for Global {
+    #[inline(always)]
+    fn allocate(&self, _layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Err(AllocError)
+    }
+
+    #[inline(always)]
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+/// The allocator of queues living in memory they don't own, like a [`StaticQueue`]. It never
+/// allocates, and deallocating is a no-op.
+///
+/// [`StaticQueue`]: crate::spsc::StaticQueue
+#[cfg(not(feature = "loom"))]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct NoAlloc;
+
+#[cfg(not(feature = "loom"))]
+unsafe impl Allocator for NoAlloc {
+    #[inline(always)]
+    fn allocate(&self, _layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Err(AllocError)
+    }
+
+    #[inline(always)]
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+/// Allocates `layout` with `alloc`, aborting through [`handle_alloc_error`] if it fails.
+///
+/// [`handle_alloc_error`]: crate::alloc::handle_alloc_error
+#[cfg(feature = "alloc")]
+#[inline]
+pub(crate) fn allocate<A: Allocator>(alloc: &A, layout: Layout) -> NonNull<u8> {
+    match alloc.allocate(layout) {
+        Ok(ptr) => ptr.cast(),
+        Err(_) => crate::alloc::handle_alloc_error(layout),
+    }
+}
+
+#[cfg(all(test, feature = "alloc", not(feature = "loom")))]
+mod test {
+    use core::num::NonZeroUsize;
+
+    use super::*;
+    use crate::atomic::{AtomicUsize, Ordering};
+
+    /// Counts the live allocations made through it.
+    #[derive(Clone, Copy)]
+    struct Counting<'a>(&'a AtomicUsize);
+
+    unsafe impl Allocator for Counting<'_> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.0.fetch_sub(1, Ordering::Relaxed);
+            unsafe { Global.deallocate(ptr, layout) }
+        }
+    }
+
+    #[test]
+    fn test_channel_in() {
+        let live = AtomicUsize::new(0);
+        let capacity = NonZeroUsize::new(4).unwrap();
+
+        let (mut tx, mut rx) = crate::spsc::channel_in(capacity, Counting(&live));
+        assert_eq!(live.load(Ordering::Relaxed), 1);
+        tx.send(1);
+        assert_eq!(rx.recv(), 1);
+        drop(tx);
+        assert_eq!(live.load(Ordering::Relaxed), 1);
+        drop(rx);
+        assert_eq!(live.load(Ordering::Relaxed), 0);
+
+        let (tx, rx) = crate::mpmc::channel_in::<usize, _>(capacity, Counting(&live));
+        let tx2 = tx.clone();
+        drop((tx, rx));
+        assert_eq!(live.load(Ordering::Relaxed), 1);
+        drop(tx2);
+        assert_eq!(live.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_sharded_channel_in() {
+        let live = AtomicUsize::new(0);
+
+        let (mut tx, mut rx) = crate::mpsc::sharded::channel_in(
+            NonZeroUsize::new(4).unwrap(),
+            NonZeroUsize::new(4).unwrap(),
+            Counting(&live),
+        );
+        // the shards array and one allocation per shard
+        assert_eq!(live.load(Ordering::Relaxed), 5);
+        tx.send(1);
+        assert_eq!(rx.recv(), 1);
+        drop((tx, rx));
+        assert_eq!(live.load(Ordering::Relaxed), 0);
+    }
+}
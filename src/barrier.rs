@@ -0,0 +1,112 @@
+//! Asymmetric memory barriers, for protocols where one side runs on every operation and the other
+//! only when a task is about to sleep.
+//!
+//! The wake protocol of the async queues is a store-load pattern on both sides: the fast side
+//! stores an index and then loads a sleeping flag, the slow side stores the flag and then loads the
+//! index again. Each side needs a full barrier between its store and its load, or both can miss
+//! each other and the sleeping task is never woken up.
+//!
+//! On Linux, the barrier of the fast side is only a compiler fence, and the slow side makes every
+//! running thread of the process execute a full barrier with `membarrier`. The process registers
+//! for it on the first barrier of either side. Elsewhere, and on kernels without `membarrier`, both
+//! sides use a `SeqCst` fence.
+
+use crate::atomic::{self, Ordering};
+
+/// The barrier between the store and the load of the side running on every operation.
+#[inline(always)]
+pub(crate) fn light() {
+    #[cfg(all(feature = "std", target_os = "linux", not(feature = "loom")))]
+    if membarrier::is_available() {
+        atomic::compiler_fence(Ordering::SeqCst);
+        return;
+    }
+
+    atomic::fence(Ordering::SeqCst);
+}
+
+/// The barrier between the store and the load of the side about to sleep.
+#[inline(always)]
+pub(crate) fn heavy() {
+    #[cfg(all(feature = "std", target_os = "linux", not(feature = "loom")))]
+    if membarrier::try_barrier() {
+        return;
+    }
+
+    atomic::fence(Ordering::SeqCst);
+}
+
+#[cfg(all(feature = "std", target_os = "linux", not(feature = "loom")))]
+mod membarrier {
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    /// From `<linux/membarrier.h>`, not exposed by `libc`.
+    const MEMBARRIER_CMD_PRIVATE_EXPEDITED: libc::c_int = 1 << 3;
+    const MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED: libc::c_int = 1 << 4;
+
+    pub(super) const UNKNOWN: u8 = 0;
+    const AVAILABLE: u8 = 1;
+    const UNAVAILABLE: u8 = 2;
+
+    /// Whether the process is registered for expedited barriers. It is never reset once known,
+    /// and whichever side registers first, both get the same answer from the kernel: a light
+    /// barrier only skips the fence if a heavy barrier is sure to use `membarrier`.
+    pub(super) static STATE: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+    /// Returns whether expedited barriers are available, registering the process on the first
+    /// call so that the light barriers are compiler fences from the start.
+    #[inline(always)]
+    pub(super) fn is_available() -> bool {
+        match STATE.load(Ordering::Relaxed) {
+            AVAILABLE => true,
+            UNAVAILABLE => false,
+            _ => register(),
+        }
+    }
+
+    #[cold]
+    fn register() -> bool {
+        // registering again is harmless, so racing threads can all try
+        let available = membarrier(MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED);
+        let state = if available { AVAILABLE } else { UNAVAILABLE };
+        STATE.store(state, Ordering::Relaxed);
+        available
+    }
+
+    /// Issues a barrier on all the running threads of the process, registering it first if needed.
+    ///
+    /// Returns `false` if the kernel doesn't support it.
+    #[cold]
+    pub(super) fn try_barrier() -> bool {
+        if !is_available() {
+            return false;
+        }
+
+        // can't fail once registered
+        let ret = membarrier(MEMBARRIER_CMD_PRIVATE_EXPEDITED);
+        debug_assert!(ret, "membarrier failed");
+        ret
+    }
+
+    fn membarrier(cmd: libc::c_int) -> bool {
+        // SAFETY: membarrier has no memory safety preconditions
+        unsafe { libc::syscall(libc::SYS_membarrier, cmd, 0, 0) == 0 }
+    }
+}
+
+#[cfg(all(test, feature = "std", target_os = "linux", not(feature = "loom")))]
+mod test {
+    use core::sync::atomic::Ordering;
+
+    use super::*;
+
+    #[test]
+    fn test_light_registers() {
+        light();
+        assert_ne!(
+            membarrier::STATE.load(Ordering::Relaxed),
+            membarrier::UNKNOWN
+        );
+        heavy();
+    }
+}
@@ -0,0 +1,55 @@
+//! Cooperative budgeting for the async paths of the queues.
+//!
+//! A task receiving in a loop from a busy queue gets `Poll::Ready` on every poll, so it never
+//! yields back to the executor and starves the other tasks of its worker. A budget caps the number
+//! of consecutive ready polls, after which the task is woken up and yields once.
+
+use core::{
+    num::NonZeroU32,
+    task::{Context, Poll},
+};
+
+pub(crate) struct Budget {
+    /// The number of consecutive ready polls allowed, `0` for no limit.
+    limit: u32,
+    remaining: u32,
+}
+
+impl Budget {
+    pub(crate) const fn unlimited() -> Self {
+        Self {
+            limit: 0,
+            remaining: 0,
+        }
+    }
+
+    pub(crate) const fn new(limit: NonZeroU32) -> Self {
+        Self {
+            limit: limit.get(),
+            remaining: limit.get(),
+        }
+    }
+
+    /// Returns `Poll::Pending` and wakes the task up if it ran out of budget, replenishing it.
+    #[inline(always)]
+    pub(crate) fn poll_proceed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.limit != 0 && self.remaining == 0 {
+            self.remaining = self.limit;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        Poll::Ready(())
+    }
+
+    /// Spends one unit of budget on a ready poll.
+    #[inline(always)]
+    pub(crate) fn spend(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+    }
+
+    /// Replenishes the budget, when the task yields on its own.
+    #[inline(always)]
+    pub(crate) fn reset(&mut self) {
+        self.remaining = self.limit;
+    }
+}
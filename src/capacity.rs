@@ -0,0 +1,92 @@
+//! Capacities of the queues, chosen either at runtime or at compile time.
+
+use core::num::NonZeroUsize;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The capacity of a queue.
+///
+/// This trait is sealed. It is implemented by [`Dynamic`] for capacities chosen at runtime, which
+/// is what the `channel` constructors use, and by [`Const`] for capacities known at compile time,
+/// which lets the compiler fold the index masking and space checks into constants.
+pub trait Capacity: sealed::Sealed + Copy {
+    /// The number of values the queue can hold.
+    fn size(&self) -> usize;
+
+    /// The number of slots in the buffer, a power of two no smaller than [`Capacity::size`].
+    fn capacity(&self) -> usize;
+
+    /// The mask wrapping an index into the buffer.
+    #[inline(always)]
+    fn mask(&self) -> usize {
+        self.capacity() - 1
+    }
+}
+
+/// A capacity chosen at runtime.
+#[derive(Clone, Copy, Debug)]
+pub struct Dynamic {
+    size: usize,
+    capacity: usize,
+    mask: usize,
+}
+
+impl Dynamic {
+    /// The buffer is rounded up to the next power of two, but the queue only holds `size` values.
+    pub(crate) fn new(size: NonZeroUsize) -> Self {
+        let size = size.get();
+        let capacity = size.next_power_of_two();
+        Self {
+            size,
+            capacity,
+            mask: capacity - 1,
+        }
+    }
+}
+
+impl sealed::Sealed for Dynamic {}
+
+impl Capacity for Dynamic {
+    #[inline(always)]
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    #[inline(always)]
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[inline(always)]
+    fn mask(&self) -> usize {
+        self.mask
+    }
+}
+
+/// A capacity of `N` values known at compile time, `N` must be a power of two.
+#[derive(Clone, Copy, Debug)]
+pub struct Const<const N: usize>;
+
+impl<const N: usize> Const<N> {
+    #[cfg(feature = "alloc")]
+    pub(crate) const fn new() -> Self {
+        const { assert!(N.is_power_of_two(), "capacity must be a power of two") };
+        Self
+    }
+}
+
+impl<const N: usize> sealed::Sealed for Const<N> {}
+
+impl<const N: usize> Capacity for Const<N> {
+    #[inline(always)]
+    fn size(&self) -> usize {
+        N
+    }
+
+    #[inline(always)]
+    fn capacity(&self) -> usize {
+        N
+    }
+}
@@ -1,14 +1,103 @@
-use core::{mem::MaybeUninit, ptr::NonNull};
+use core::{
+    alloc::{Layout, LayoutError},
+    mem::{MaybeUninit, align_of, size_of},
+    ptr::NonNull,
+};
 
-use crate::atomic::AtomicUsize;
+use crate::{atomic::AtomicUsize, padded::Padded};
 
-#[repr(align(64))]
-#[cfg_attr(all(target_arch = "aarch64", target_os = "macos"), repr(align(128)))]
+#[repr(C)]
 pub(crate) struct Cell<T> {
     pub(crate) epoch: AtomicUsize,
     pub(crate) data: MaybeUninit<T>,
 }
 
+mod sealed {
+    use super::*;
+
+    pub trait Sealed {
+        /// The layout of a buffer of `capacity` cells.
+        fn array<T>(capacity: usize) -> Result<Layout, LayoutError>;
+
+        /// The distance in bytes between two consecutive slots.
+        fn stride<T>() -> usize;
+
+        /// The slot holding `index`, `mask` being the mask of a power-of-two capacity.
+        fn slot<T>(index: usize, mask: usize) -> usize;
+    }
+}
+
+/// The layout of the cells in the buffer of the `mpsc`, `mpmc` and `spmc` queues.
+///
+/// This trait is sealed. Every cell holds a value and the atomic epoch guarding it.
+/// [`PaddedCells`], the default, gives each cell its own cache line, so that threads working on
+/// adjacent slots never contend. [`CompactCells`] packs them densely to save memory for small
+/// values, and spreads consecutive indices over different cache lines instead.
+pub trait CellLayout: sealed::Sealed + Copy {}
+
+/// Every cell is padded to a cache line.
+#[derive(Clone, Copy, Debug)]
+pub struct PaddedCells;
+
+impl sealed::Sealed for PaddedCells {
+    #[inline(always)]
+    fn array<T>(capacity: usize) -> Result<Layout, LayoutError> {
+        Layout::array::<Padded<Cell<T>>>(capacity)
+    }
+
+    #[inline(always)]
+    fn stride<T>() -> usize {
+        size_of::<Padded<Cell<T>>>()
+    }
+
+    #[inline(always)]
+    fn slot<T>(index: usize, mask: usize) -> usize {
+        index & mask
+    }
+}
+
+impl CellLayout for PaddedCells {}
+
+/// Cells are packed densely, e.g. 16 bytes per cell for `u64` instead of a whole cache line.
+///
+/// Consecutive indices are mapped to slots on different cache lines, so that producers and
+/// consumers working on neighbouring indices still mostly touch different lines. Queues fitting
+/// in a single cache line are left as they are.
+#[derive(Clone, Copy, Debug)]
+pub struct CompactCells;
+
+impl sealed::Sealed for CompactCells {
+    #[inline(always)]
+    fn array<T>(capacity: usize) -> Result<Layout, LayoutError> {
+        Layout::array::<Cell<T>>(capacity)
+    }
+
+    #[inline(always)]
+    fn stride<T>() -> usize {
+        size_of::<Cell<T>>()
+    }
+
+    #[inline(always)]
+    fn slot<T>(index: usize, mask: usize) -> usize {
+        let index = index & mask;
+
+        // log2 of the number of cells sharing a cache line
+        let shift = (align_of::<Padded<()>>() / size_of::<Cell<T>>())
+            .checked_ilog2()
+            .unwrap_or(0);
+        let bits = mask.count_ones();
+        if shift == 0 || bits <= shift {
+            return index;
+        }
+
+        // rotating the index bits puts consecutive indices `2^(bits - shift)` slots apart, i.e.
+        // on the same position of different lines
+        ((index << shift) & mask) | (index >> (bits - shift))
+    }
+}
+
+impl CellLayout for CompactCells {}
+
 pub(crate) struct CellPtr<T> {
     ptr: NonNull<Cell<T>>,
 }
@@ -21,6 +110,13 @@ impl<T> CellPtr<T> {
         unsafe { _field!(Cell<T>, self.ptr, data, T).read() }
     }
 
+    /// # Safety
+    /// The value must be initialised, and stay so for `'a`
+    #[inline(always)]
+    pub(crate) unsafe fn as_ref<'a>(&self) -> &'a T {
+        unsafe { _field!(Cell<T>, self.ptr, data, T).as_ref() }
+    }
+
     #[inline(always)]
     pub(crate) fn set(&self, value: T) {
         unsafe { _field!(Cell<T>, self.ptr, data, T).write(value) }
@@ -46,3 +142,33 @@ impl<T> From<NonNull<Cell<T>>> for CellPtr<T> {
         Self { ptr: value }
     }
 }
+
+#[cfg(all(test, not(feature = "loom")))]
+mod test {
+    use super::{sealed::Sealed, *};
+
+    #[test]
+    fn test_compact_slots() {
+        let cells_per_line = align_of::<Padded<()>>() / size_of::<Cell<u64>>();
+
+        for capacity in [1, 2, cells_per_line, 64, 1024] {
+            let mask = capacity - 1;
+            let mut seen = [false; 1024];
+            for index in 0..capacity {
+                let slot = CompactCells::slot::<u64>(index, mask);
+                assert!(!seen[slot]);
+                seen[slot] = true;
+
+                // wraps around with the index
+                assert_eq!(CompactCells::slot::<u64>(index + capacity, mask), slot);
+            }
+        }
+
+        // neighbouring indices are on different lines
+        let mask = 1023;
+        for index in 0..mask {
+            let line = |index| CompactCells::slot::<u64>(index, mask) / cells_per_line;
+            assert_ne!(line(index), line(index + 1));
+        }
+    }
+}
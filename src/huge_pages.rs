@@ -0,0 +1,242 @@
+//! An allocator backing queues with huge pages, optionally bound to a NUMA node.
+
+use core::{alloc::Layout, ffi::c_ulong, ptr::NonNull};
+
+use crate::{AllocError, Allocator};
+
+/// The size of the huge pages, the default on x86-64 and aarch64 kernels.
+const HUGE_PAGE_SIZE: usize = 2 << 20;
+
+/// Not exposed by `libc`, from `<linux/mempolicy.h>`.
+const MPOL_BIND: libc::c_int = 2;
+
+/// The number of NUMA nodes the node mask passed to `mbind` can address.
+const MAX_NODES: usize = 1024;
+
+/// An [`Allocator`] mapping queues on huge pages, to cut down the TLB misses of large rings.
+///
+/// Every allocation is an anonymous private mapping, 2 MiB aligned and rounded up to a multiple of
+/// 2 MiB. By default the kernel is asked to back it with transparent huge pages through
+/// `madvise(MADV_HUGEPAGE)`; with [`HugePages::explicit`] it is first mapped with `MAP_HUGETLB`
+/// from the pool of reserved huge pages, falling back to transparent huge pages when the pool is
+/// empty.
+///
+/// The memory can be bound to the NUMA node of the consumer with [`HugePages::numa_node`], and
+/// faulted in when the queue is created with [`HugePages::prefault`], so that the first pass over
+/// the ring doesn't page-fault on the hot path.
+///
+/// Since every allocation takes at least one huge page, this is meant for large queues.
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use gil::{HugePages, spsc::channel_in};
+///
+/// let alloc = HugePages::new().prefault(true);
+/// let (mut tx, mut rx) = channel_in::<u64, _>(NonZeroUsize::new(1 << 20).unwrap(), alloc);
+///
+/// tx.send(1);
+/// assert_eq!(rx.recv(), 1);
+/// ```
+#[derive(Clone, Copy, Default, Debug)]
+pub struct HugePages {
+    explicit: bool,
+    prefault: bool,
+    numa_node: Option<u16>,
+}
+
+impl HugePages {
+    /// An allocator using transparent huge pages, on no particular NUMA node and without
+    /// pre-faulting.
+    pub const fn new() -> Self {
+        Self {
+            explicit: false,
+            prefault: false,
+            numa_node: None,
+        }
+    }
+
+    /// Maps the memory from the reserved huge pages with `MAP_HUGETLB`, falling back to transparent
+    /// huge pages if there aren't enough of them.
+    pub const fn explicit(mut self) -> Self {
+        self.explicit = true;
+        self
+    }
+
+    /// Binds the memory to the NUMA `node` with `mbind`.
+    ///
+    /// The binding is only a placement preference: if the kernel refuses it, e.g. because it has
+    /// no NUMA support or there is no such node, the memory is left unbound.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` is not below 1024.
+    pub const fn numa_node(mut self, node: u16) -> Self {
+        assert!((node as usize) < MAX_NODES, "NUMA node out of range");
+        self.numa_node = Some(node);
+        self
+    }
+
+    /// Whether to fault in all the pages when allocating.
+    pub const fn prefault(mut self, prefault: bool) -> Self {
+        self.prefault = prefault;
+        self
+    }
+
+    fn map(&self, len: usize) -> Result<NonNull<u8>, AllocError> {
+        let map = |len, flags| {
+            // SAFETY: anonymous mapping at an address chosen by the kernel
+            let ptr = unsafe {
+                libc::mmap(
+                    core::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | flags,
+                    -1,
+                    0,
+                )
+            };
+            // mmap never returns null on success when not asked for a fixed address
+            (ptr != libc::MAP_FAILED).then(|| unsafe { NonNull::new_unchecked(ptr.cast::<u8>()) })
+        };
+
+        if self.explicit
+            && let Some(ptr) = map(len, libc::MAP_HUGETLB)
+        {
+            return Ok(ptr);
+        }
+
+        // transparent huge pages only back huge page aligned ranges, so the mapping is made one
+        // huge page longer and trimmed down to an aligned range
+        let base = map(len + HUGE_PAGE_SIZE, 0).ok_or(AllocError)?;
+        let head = base.align_offset(HUGE_PAGE_SIZE);
+        let tail = HUGE_PAGE_SIZE - head;
+        // SAFETY: both ranges are within the mapping, and not handed out
+        unsafe {
+            if head != 0 {
+                libc::munmap(base.as_ptr().cast(), head);
+            }
+            if tail != 0 {
+                libc::munmap(base.as_ptr().add(head + len).cast(), tail);
+            }
+        }
+        let ptr = unsafe { base.add(head) };
+
+        // only a hint, the mapping is usable even if the kernel has no transparent huge pages
+        unsafe { libc::madvise(ptr.as_ptr().cast(), len, libc::MADV_HUGEPAGE) };
+        Ok(ptr)
+    }
+
+    /// Asks the kernel to bind the range to `node`, which it may refuse.
+    fn bind(ptr: NonNull<u8>, len: usize, node: u16) {
+        let mut mask = [0 as c_ulong; MAX_NODES / c_ulong::BITS as usize];
+        let bits = c_ulong::BITS as usize;
+        mask[node as usize / bits] |= 1 << (node as usize % bits);
+
+        // SAFETY: the range is a mapping we own, and the mask holds MAX_NODES bits
+        unsafe {
+            libc::syscall(
+                libc::SYS_mbind,
+                ptr.as_ptr(),
+                len,
+                MPOL_BIND,
+                mask.as_ptr(),
+                MAX_NODES,
+                0,
+            )
+        };
+    }
+}
+
+/// The length of the mapping backing `layout`.
+#[inline(always)]
+fn mapping_len(layout: Layout) -> usize {
+    layout.size().next_multiple_of(HUGE_PAGE_SIZE)
+}
+
+unsafe impl Allocator for HugePages {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        // mappings are aligned to huge pages
+        if layout.align() > HUGE_PAGE_SIZE {
+            return Err(AllocError);
+        }
+
+        let len = mapping_len(layout);
+        let ptr = self.map(len)?;
+
+        // the policy has to be set before the pages are faulted in. Failing to bind isn't worth
+        // failing the allocation over, which would abort the process
+        if let Some(node) = self.numa_node {
+            Self::bind(ptr, len, node);
+        }
+
+        if self.prefault {
+            for offset in (0..len).step_by(page_size()) {
+                // SAFETY: within the mapping, whose contents are not initialised yet anyway
+                unsafe { ptr.as_ptr().add(offset).write_volatile(0) };
+            }
+        }
+
+        Ok(NonNull::slice_from_raw_parts(ptr, len))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { libc::munmap(ptr.as_ptr().cast(), mapping_len(layout)) };
+    }
+}
+
+#[inline(always)]
+fn page_size() -> usize {
+    // SAFETY: sysconf has no preconditions
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+#[cfg(test)]
+mod test {
+    use core::num::NonZeroUsize;
+
+    use super::*;
+
+    #[test]
+    fn test_channel_in() {
+        let capacity = NonZeroUsize::new(1 << 16).unwrap();
+        let alloc = HugePages::new().explicit().prefault(true);
+
+        let (mut tx, mut rx) = crate::spsc::channel_in(capacity, alloc);
+        for i in 0..capacity.get() {
+            tx.send(i);
+        }
+        for i in 0..capacity.get() {
+            assert_eq!(rx.recv(), i);
+        }
+
+        let (tx, mut rx) = crate::mpmc::channel_in(capacity, HugePages::new());
+        let mut tx2 = tx.clone();
+        drop(tx);
+        tx2.send(1);
+        assert_eq!(rx.recv(), 1);
+    }
+
+    #[test]
+    fn test_unknown_numa_node() {
+        let alloc = HugePages::new().numa_node(1000);
+        let (mut tx, mut rx) = crate::spsc::channel_in(NonZeroUsize::new(16).unwrap(), alloc);
+        tx.send(1);
+        assert_eq!(rx.recv(), 1);
+    }
+
+    #[test]
+    fn test_mapping_len() {
+        let alloc = HugePages::new();
+        let layout = Layout::from_size_align(100, 64).unwrap();
+        let block = alloc.allocate(layout).unwrap();
+        assert_eq!(block.len(), HUGE_PAGE_SIZE);
+        // transparent huge pages only back aligned ranges
+        assert_eq!(block.cast::<u8>().as_ptr() as usize % HUGE_PAGE_SIZE, 0);
+        unsafe { alloc.deallocate(block.cast(), layout) };
+
+        let layout = Layout::from_size_align(HUGE_PAGE_SIZE + 1, 8).unwrap();
+        assert_eq!(mapping_len(layout), 2 * HUGE_PAGE_SIZE);
+    }
+}
@@ -0,0 +1,66 @@
+//! The blocking iterator shared by the receivers of all the flavors.
+
+use crate::Backoff;
+
+/// Returns an iterator receiving values from `receiver` with `try_recv`, waiting for each one until
+/// `is_closed` returns `true` and there is nothing left to receive.
+pub(crate) fn blocking<'a, R, T: 'a>(
+    receiver: &'a mut R,
+    mut try_recv: impl FnMut(&mut R) -> Option<T> + 'a,
+    is_closed: impl Fn(&R) -> bool + 'a,
+) -> impl Iterator<Item = T> + 'a {
+    core::iter::from_fn(move || {
+        let mut backoff = Backoff::with_spin_count(128);
+        loop {
+            if let Some(value) = try_recv(receiver) {
+                return Some(value);
+            }
+            // a value may have been sent right before the channel was closed
+            if is_closed(receiver) {
+                return try_recv(receiver);
+            }
+            backoff.backoff();
+        }
+    })
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod test {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    /// Returns the values of a script, one per poll, and counts as closed once only the last one
+    /// is left.
+    struct Scripted(VecDeque<Option<usize>>);
+
+    impl Scripted {
+        fn try_recv(&mut self) -> Option<usize> {
+            self.0.pop_front().flatten()
+        }
+
+        fn is_closed(&self) -> bool {
+            self.0.len() <= 1
+        }
+    }
+
+    #[test]
+    fn test_blocking() {
+        let mut receiver = Scripted(VecDeque::from([
+            Some(0),
+            None,
+            None,
+            Some(1),
+            None,
+            Some(2),
+        ]));
+        let mut iter = blocking(&mut receiver, Scripted::try_recv, Scripted::is_closed);
+
+        // waits through the empty polls
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.next(), Some(1));
+        // the value found closed, sent right before closing
+        assert_eq!(iter.next(), Some(2));
+        assert_eq!(iter.next(), None);
+    }
+}
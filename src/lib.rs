@@ -1,12 +1,15 @@
 #![no_std]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "alloc")]
 extern crate alloc as alloc_crate;
 #[cfg(any(test, feature = "std"))]
 extern crate std;
 
-#[cfg(not(feature = "loom"))]
+#[cfg(all(feature = "alloc", not(feature = "loom")))]
 pub(crate) use alloc_crate::alloc;
+#[cfg(feature = "alloc")]
 pub(crate) use alloc_crate::boxed::Box;
 
 #[allow(unused_imports)]
@@ -49,12 +52,41 @@ macro_rules! _field {
     };
 }
 
+mod allocator;
 mod backoff;
+#[cfg(feature = "async")]
+mod barrier;
+#[cfg(feature = "async")]
+mod budget;
+mod capacity;
+#[cfg(feature = "alloc")]
 mod cell;
+#[cfg(all(feature = "huge_pages", target_os = "linux"))]
+mod huge_pages;
+mod iter;
+#[cfg(feature = "alloc")]
 pub mod mpmc;
+#[cfg(feature = "alloc")]
 pub mod mpsc;
+#[cfg(feature = "alloc")]
+pub mod oneshot;
 mod padded;
+#[cfg(feature = "alloc")]
+pub mod priority;
+#[cfg(feature = "alloc")]
+pub mod rendezvous;
+#[cfg(feature = "alloc")]
+pub mod select;
+#[cfg(all(feature = "shm", unix, not(feature = "loom")))]
+mod shm;
+#[cfg(feature = "alloc")]
 pub mod spmc;
 pub mod spsc;
 
+pub use allocator::{AllocError, Allocator, Global};
 pub use backoff::Backoff;
+pub use capacity::{Capacity, Const, Dynamic};
+#[cfg(feature = "alloc")]
+pub use cell::{CellLayout, CompactCells, PaddedCells};
+#[cfg(all(feature = "huge_pages", target_os = "linux"))]
+pub use huge_pages::HugePages;
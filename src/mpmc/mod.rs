@@ -23,7 +23,12 @@
 
 use core::num::NonZeroUsize;
 
-pub use self::{receiver::Receiver, sender::Sender};
+use crate::{Allocator, CompactCells, Const, Dynamic, Global};
+
+pub use self::{
+    receiver::Receiver,
+    sender::{Sender, WeakSender},
+};
 
 mod queue;
 mod receiver;
@@ -51,7 +56,75 @@ pub mod sharded;
 /// let (tx, rx) = channel::<usize>(NonZeroUsize::new(1024).unwrap());
 /// ```
 pub fn channel<T>(capacity: NonZeroUsize) -> (Sender<T>, Receiver<T>) {
-    let queue = queue::QueuePtr::with_size(capacity);
+    channel_in(capacity, Global)
+}
+
+/// Creates a new multi-producer multi-consumer (MPMC) queue in the given allocator.
+///
+/// Same as [`channel`], but the queue is allocated with `alloc` instead of the [`Global`]
+/// allocator. Every handle keeps a clone of `alloc`, the last one dropped frees the queue with it.
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use gil::{Global, mpmc::channel_in};
+///
+/// let (tx, rx) = channel_in::<usize, _>(NonZeroUsize::new(1024).unwrap(), Global);
+/// ```
+pub fn channel_in<T, A: Allocator + Clone>(
+    capacity: NonZeroUsize,
+    alloc: A,
+) -> (Sender<T, A>, Receiver<T, A>) {
+    let queue = queue::QueuePtr::with_capacity_in(Dynamic::new(capacity), alloc);
+    (Sender::new(queue.clone()), Receiver::new(queue))
+}
+
+/// Creates a new multi-producer multi-consumer (MPMC) queue with a capacity known at compile time.
+///
+/// Same as [`channel`], but the capacity `N` is part of the types of the handles, so the compiler
+/// folds the index masking and the space checks into constants. `N` must be a power of two, which
+/// is checked at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use gil::{Const, Global, mpmc::{Receiver, Sender, channel_const}};
+///
+/// let (tx, rx): (Sender<usize, Global, Const<1024>>, Receiver<usize, Global, Const<1024>>) =
+///     channel_const::<usize, 1024>();
+/// ```
+pub fn channel_const<T, const N: usize>()
+-> (Sender<T, Global, Const<N>>, Receiver<T, Global, Const<N>>) {
+    let queue = queue::QueuePtr::with_capacity_in(Const::<N>::new(), Global);
+    (Sender::new(queue.clone()), Receiver::new(queue))
+}
+
+/// Creates a new multi-producer multi-consumer (MPMC) queue with densely packed cells.
+///
+/// Same as [`channel`], but the cells of the buffer are not padded to a cache line, which saves
+/// most of the memory of queues of small values, e.g. 16 bytes per `u64` instead of a whole line.
+/// See [`CompactCells`] for how contention between neighbouring slots is kept low.
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use gil::{CompactCells, Dynamic, Global, mpmc::{Receiver, Sender, channel_compact}};
+///
+/// let (tx, rx): (
+///     Sender<u64, Global, Dynamic, CompactCells>,
+///     Receiver<u64, Global, Dynamic, CompactCells>,
+/// ) = channel_compact::<u64>(NonZeroUsize::new(1024).unwrap());
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn channel_compact<T>(
+    capacity: NonZeroUsize,
+) -> (
+    Sender<T, Global, Dynamic, CompactCells>,
+    Receiver<T, Global, Dynamic, CompactCells>,
+) {
+    let queue = queue::QueuePtr::with_capacity_in(Dynamic::new(capacity), Global);
     (Sender::new(queue.clone()), Receiver::new(queue))
 }
 
@@ -61,6 +134,144 @@ mod test {
 
     use crate::thread;
 
+    #[test]
+    fn test_channel_const() {
+        let (mut tx, mut rx) = channel_const::<usize, 4>();
+        for i in 0..4 {
+            tx.try_send(i).unwrap();
+        }
+        assert!(tx.try_send(4).is_err());
+
+        for i in 0..4 {
+            assert_eq!(rx.try_recv(), Some(i));
+        }
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_is_closed() {
+        let (tx, rx) = channel::<usize>(NonZeroUsize::new(4).unwrap());
+        let tx2 = tx.clone();
+        let rx2 = rx.clone();
+        drop(tx);
+        assert!(!rx.is_closed());
+        drop(rx);
+        assert!(!tx2.is_closed());
+        drop(rx2);
+        assert!(tx2.is_closed());
+
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(4).unwrap());
+        tx.send(0);
+        drop(tx);
+        assert!(rx.is_closed() && rx.clone().is_closed());
+        assert!(rx.iter().eq([0]));
+    }
+
+    #[test]
+    fn test_weak_sender() {
+        let (tx, mut rx) = channel::<usize>(NonZeroUsize::new(4).unwrap());
+        let weak = tx.downgrade();
+        let mut tx2 = weak.clone().upgrade().unwrap();
+        tx2.send(1);
+        assert_eq!(rx.try_recv(), Some(1));
+
+        drop(tx);
+        drop(tx2);
+        assert!(rx.is_closed());
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_close() {
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(4).unwrap());
+        tx.send(0);
+        rx.close();
+        assert!(tx.is_closed() && rx.is_closed());
+        assert_eq!(tx.try_send(1), Err(1));
+        tx.send(2);
+        assert_eq!(rx.try_recv(), Some(0));
+        assert_eq!(rx.try_recv(), None);
+
+        let (tx, rx) = channel::<usize>(NonZeroUsize::new(4).unwrap());
+        drop(rx);
+        assert!(tx.is_closed());
+    }
+
+    #[test]
+    fn test_send_waiting_when_receiver_dropped() {
+        use std::sync::Arc;
+
+        let item = Arc::new(());
+        let (mut tx, rx) = channel::<Arc<()>>(NonZeroUsize::new(2).unwrap());
+        tx.send(item.clone());
+        tx.send(item.clone());
+
+        let waiting = {
+            let item = item.clone();
+            let mut tx = tx.clone();
+            thread::spawn(move || tx.send(item))
+        };
+        thread::sleep(core::time::Duration::from_millis(50));
+        drop(rx);
+        waiting.join().unwrap();
+
+        drop(tx);
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn test_drain() {
+        use std::sync::Arc;
+
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(8).unwrap());
+        for i in 0..5 {
+            tx.send(i);
+        }
+
+        assert!(rx.drain().eq(0..5));
+        assert!(rx.try_iter().next().is_none());
+        tx.send(5);
+        assert!(rx.try_iter().eq(5..6));
+
+        let item = Arc::new(());
+        let (mut tx, mut rx) = channel::<Arc<()>>(NonZeroUsize::new(8).unwrap());
+        for _ in 0..5 {
+            tx.send(item.clone());
+        }
+        rx.clear();
+        assert_eq!(Arc::strong_count(&item), 1);
+        assert!(rx.try_recv().is_none());
+
+        tx.send(item.clone());
+        assert_eq!(rx.iter().next().map(|v| Arc::ptr_eq(&v, &item)), Some(true));
+    }
+
+    #[test]
+    fn test_channel_compact() {
+        use std::sync::Arc;
+
+        let (mut tx, mut rx) = channel_compact::<usize>(NonZeroUsize::new(64).unwrap());
+        for round in 0..3 {
+            for i in 0..64 {
+                tx.try_send(round * 64 + i).unwrap();
+            }
+            assert!(tx.try_send(0).is_err());
+
+            for i in 0..64 {
+                assert_eq!(rx.try_recv(), Some(round * 64 + i));
+            }
+            assert!(rx.try_recv().is_none());
+        }
+
+        let item = Arc::new(());
+        let (mut tx, rx) = channel_compact::<Arc<()>>(NonZeroUsize::new(64).unwrap());
+        for _ in 0..40 {
+            tx.send(item.clone());
+        }
+        drop((tx, rx));
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
     #[test]
     fn basic() {
         const THREADS: u32 = 10;
@@ -1,36 +1,76 @@
 use core::cmp::Ordering as Cmp;
 
-use crate::{atomic::Ordering, mpmc::queue::QueuePtr};
+use crate::{
+    Allocator, Capacity, CellLayout, Dynamic, Global, PaddedCells, atomic::Ordering,
+    mpmc::queue::QueuePtr,
+};
 
 /// The producer end of the MPMC queue.
 ///
 /// This struct is `Clone` and `Send`. It can be shared across threads by cloning it.
-#[derive(Clone)]
-pub struct Sender<T> {
-    ptr: QueuePtr<T>,
+pub struct Sender<T, A: Allocator = Global, C: Capacity = Dynamic, L: CellLayout = PaddedCells> {
+    ptr: QueuePtr<T, A, C, L>,
     local_tail: usize,
 }
 
-impl<T> Sender<T> {
-    pub(crate) fn new(queue_ptr: QueuePtr<T>) -> Self {
+impl<T, A: Allocator, C: Capacity, L: CellLayout> Sender<T, A, C, L> {
+    pub(crate) fn new(queue_ptr: QueuePtr<T, A, C, L>) -> Self {
+        queue_ptr.senders().fetch_add(1, Ordering::AcqRel);
         Self {
             ptr: queue_ptr,
             local_tail: 0,
         }
     }
 
+    /// Returns `true` if a receiver closed the queue with [`Receiver::close`], or if all the
+    /// receivers were dropped.
+    ///
+    /// Sending fails from then on. This can be used to cancel the work producing the values
+    /// early.
+    ///
+    /// [`Receiver::close`]: super::Receiver::close
+    pub fn is_closed(&self) -> bool {
+        self.ptr.closed().load(Ordering::Acquire)
+    }
+
     /// Sends a value into the queue, blocking if necessary.
     ///
     /// This method uses a spin loop to wait for available space in the queue.
     /// For a non-blocking alternative, use [`Sender::try_send`].
+    ///
+    /// If the queue is closed, the value is dropped and this returns right away. Use
+    /// [`Sender::try_send`] to get it back instead. A send already waiting for a slot when the
+    /// queue is closed still completes once a receiver frees it, or drops the value once
+    /// all the receivers are dropped.
     pub fn send(&mut self, value: T) {
+        if self.ptr.closed().load(Ordering::Relaxed) {
+            return;
+        }
+
         // fetch_add means we are the only ones who can access the cell at this idx
         let tail = self.ptr.tail().fetch_add(1, Ordering::Relaxed);
         let next = tail.wrapping_add(1);
 
         let cell = self.ptr.at(tail);
         let mut backoff = crate::Backoff::with_spin_count(128);
-        while cell.epoch().load(Ordering::Acquire) != tail {
+        loop {
+            let epoch = cell.epoch().load(Ordering::Acquire);
+            if epoch == tail {
+                break;
+            }
+
+            // with no receiver left the cell is never freed, so free it in their place, dropping
+            // both the value left from the previous lap and this one
+            if epoch == tail.wrapping_sub(self.ptr.capacity()).wrapping_add(1)
+                && self.ptr.receivers().load(Ordering::Acquire) == 0
+            {
+                unsafe { cell.drop_in_place() };
+                cell.epoch()
+                    .store(tail.wrapping_add(self.ptr.capacity()), Ordering::Release);
+                self.local_tail = next;
+                return;
+            }
+
             backoff.backoff();
         }
 
@@ -44,8 +84,12 @@ impl<T> Sender<T> {
     /// # Returns
     ///
     /// * `Ok(())` if the value was successfully sent.
-    /// * `Err(value)` if the queue is full, returning the original value.
+    /// * `Err(value)` if the queue is full or closed, returning the original value.
     pub fn try_send(&mut self, value: T) -> Result<(), T> {
+        if self.ptr.closed().load(Ordering::Relaxed) {
+            return Err(value);
+        }
+
         let mut backoff = crate::Backoff::with_spin_count(16);
 
         let cell = loop {
@@ -87,4 +131,84 @@ impl<T> Sender<T> {
     }
 }
 
-unsafe impl<T: Send> Send for Sender<T> {}
+impl<T, A: Allocator + Clone, C: Capacity, L: CellLayout> Sender<T, A, C, L> {
+    /// Creates a [`WeakSender`] for this queue.
+    ///
+    /// The weak handle doesn't count as a sender, so it doesn't keep the queue open: once all
+    /// the [`Sender`]s are dropped, the queue is disconnected even if weak handles remain.
+    pub fn downgrade(&self) -> WeakSender<T, A, C, L> {
+        WeakSender {
+            ptr: self.ptr.clone(),
+        }
+    }
+}
+
+impl<T, A: Allocator + Clone, C: Capacity, L: CellLayout> Clone for Sender<T, A, C, L> {
+    fn clone(&self) -> Self {
+        self.ptr.senders().fetch_add(1, Ordering::AcqRel);
+        Self {
+            ptr: self.ptr.clone(),
+            local_tail: self.local_tail,
+        }
+    }
+}
+
+impl<T, A: Allocator, C: Capacity, L: CellLayout> Drop for Sender<T, A, C, L> {
+    fn drop(&mut self) {
+        self.ptr.senders().fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+unsafe impl<T: Send, A: Allocator + Send, C: Capacity, L: CellLayout> Send for Sender<T, A, C, L> {}
+
+/// A handle to the queue that doesn't keep it open, created with [`Sender::downgrade`].
+///
+/// Useful when a consumer needs a way to send to its own queue without preventing it from
+/// being disconnected, as with an actor holding a handle to its mailbox.
+pub struct WeakSender<T, A: Allocator = Global, C: Capacity = Dynamic, L: CellLayout = PaddedCells>
+{
+    ptr: QueuePtr<T, A, C, L>,
+}
+
+impl<T, A: Allocator + Clone, C: Capacity, L: CellLayout> WeakSender<T, A, C, L> {
+    /// Attempts to turn this into a [`Sender`].
+    ///
+    /// Returns `None` if all the senders were already dropped.
+    pub fn upgrade(&self) -> Option<Sender<T, A, C, L>> {
+        let senders = self.ptr.senders();
+        let mut count = senders.load(Ordering::Relaxed);
+        loop {
+            // once the count reached 0 the queue is disconnected for good
+            if count == 0 {
+                return None;
+            }
+            match senders.compare_exchange_weak(
+                count,
+                count + 1,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(cur) => count = cur,
+            }
+        }
+
+        Some(Sender {
+            ptr: self.ptr.clone(),
+            local_tail: 0,
+        })
+    }
+}
+
+impl<T, A: Allocator + Clone, C: Capacity, L: CellLayout> Clone for WeakSender<T, A, C, L> {
+    fn clone(&self) -> Self {
+        Self {
+            ptr: self.ptr.clone(),
+        }
+    }
+}
+
+unsafe impl<T: Send, A: Allocator + Send, C: Capacity, L: CellLayout> Send
+    for WeakSender<T, A, C, L>
+{
+}
@@ -24,10 +24,10 @@ use core::num::NonZeroUsize;
 
 mod receiver;
 mod sender;
-use crate::spsc::shards::ShardsPtr;
+use crate::{Allocator, Global, spsc::shards::ShardsPtr};
 
-pub use receiver::{ReadGuard, Receiver};
-pub use sender::Sender;
+pub use receiver::{IntoIter, ReadGuard, Receiver};
+pub use sender::{Sender, WeakSender};
 
 /// Creates a new sharded multi-producer multi-consumer channel.
 ///
@@ -45,13 +45,25 @@ pub fn channel<T>(
     max_shards: NonZeroUsize,
     capacity_per_shard: NonZeroUsize,
 ) -> (sender::Sender<T>, receiver::Receiver<T>) {
+    channel_in(max_shards, capacity_per_shard, Global)
+}
+
+/// Creates a new sharded multi-producer multi-consumer channel in the given allocator.
+///
+/// Same as [`channel`], but the shards are allocated with `alloc` instead of the [`Global`]
+/// allocator.
+pub fn channel_in<T, A: Allocator + Clone>(
+    max_shards: NonZeroUsize,
+    capacity_per_shard: NonZeroUsize,
+    alloc: A,
+) -> (sender::Sender<T, A>, receiver::Receiver<T, A>) {
     debug_assert_ne!(max_shards.get(), 0, "number of shards must be > 0");
     debug_assert!(
         max_shards.is_power_of_two(),
         "number of shards must be a power of 2"
     );
 
-    let shards = ShardsPtr::new(max_shards, capacity_per_shard);
+    let shards = ShardsPtr::new(max_shards, capacity_per_shard, alloc);
 
     (
         sender::Sender::new(shards.clone(), max_shards),
@@ -103,6 +115,105 @@ mod test {
         });
     }
 
+    #[test]
+    fn test_is_closed() {
+        let (tx, rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let tx2 = tx.try_clone().unwrap();
+        let rx2 = rx.try_clone().unwrap();
+        drop(tx);
+        assert!(!rx.is_closed());
+        drop(rx);
+        assert!(!tx2.is_closed());
+        drop(rx2);
+        assert!(tx2.is_closed());
+
+        let (mut tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        tx.send(0);
+        drop(tx);
+        assert!(rx.is_closed());
+        assert!(rx.iter().eq([0]));
+    }
+
+    #[test]
+    fn test_weak_sender() {
+        let (tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let weak = tx.downgrade();
+        let mut tx2 = weak.clone().upgrade().unwrap();
+        assert!(weak.upgrade().is_none(), "no shard left");
+        tx2.send(1);
+        assert_eq!(rx.try_recv(), Some(1));
+
+        drop(tx2);
+        assert!(weak.upgrade().is_none());
+        drop(tx);
+        assert!(rx.is_closed());
+    }
+
+    #[test]
+    fn test_close() {
+        let (mut tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let mut tx2 = tx.try_clone().unwrap();
+        tx.send(0);
+        rx.close();
+        assert!(tx.is_closed() && tx2.is_closed() && rx.is_closed());
+        assert_eq!(tx2.try_send(1), Err(1));
+        tx.send(2);
+        assert!(rx.drain().eq([0]));
+    }
+
+    #[test]
+    fn test_drain() {
+        let (tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let mut tx2 = tx.try_clone().unwrap();
+        let mut tx1 = tx;
+        for i in 0..4 {
+            tx1.send(i);
+            tx2.send(4 + i);
+        }
+
+        let mut drained = rx.drain().collect::<vec::Vec<_>>();
+        drained.sort();
+        assert!(drained.into_iter().eq(0..8));
+
+        tx1.send(8);
+        tx2.send(9);
+        rx.clear();
+        assert!(rx.try_iter().next().is_none());
+    }
+
+    #[test]
+    fn test_read_guard_owned() {
+        use alloc_crate::string::{String, ToString};
+
+        let (mut tx, mut rx) =
+            channel::<String>(NonZeroUsize::new(1).unwrap(), NonZeroUsize::new(8).unwrap());
+        for i in 0..6 {
+            tx.send(i.to_string());
+        }
+
+        let mut guard = rx.read_buffer();
+        assert_eq!(guard.len(), 6);
+        // moved out by hand, advancing doesn't drop it again
+        let first = unsafe { core::ptr::read(&guard[0]) };
+        guard.advance(1);
+        assert_eq!(first, "0");
+        guard.discard(1);
+        assert_eq!(guard[0], "2");
+        assert!(guard.drain().take(2).eq(["2", "3"]));
+        assert_eq!(guard.len(), 2);
+        drop(guard);
+
+        let guard = rx.read_buffer();
+        assert!(guard.into_iter().take(1).eq(["4"]));
+        assert_eq!(rx.try_recv().as_deref(), Some("5"));
+        assert_eq!(rx.try_recv(), None);
+    }
+
     #[test]
     fn multiple_senders_multiple_receivers_blocking() {
         use std::sync::Arc;
@@ -265,6 +376,147 @@ mod test {
         }
     }
 
+    #[test]
+    fn multiple_receivers_read_buffer() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        const SHARDS: usize = 4;
+        const MESSAGES: usize = 2000;
+
+        let (tx, rx) = channel(
+            NonZeroUsize::new(SHARDS).unwrap(),
+            NonZeroUsize::new(64).unwrap(),
+        );
+        let total_received = Arc::new(AtomicUsize::new(0));
+        let total_sum = Arc::new(AtomicUsize::new(0));
+
+        thread::scope(|s| {
+            let mut senders: vec::Vec<_> = (1..SHARDS).map(|_| tx.try_clone().unwrap()).collect();
+            senders.push(tx);
+            for (t, mut tx) in senders.into_iter().enumerate() {
+                s.spawn(move || {
+                    for i in 0..MESSAGES {
+                        tx.send(t * MESSAGES + i);
+                    }
+                });
+            }
+
+            let mut receivers: vec::Vec<_> = (1..SHARDS).map(|_| rx.try_clone().unwrap()).collect();
+            receivers.push(rx);
+            for mut rx in receivers {
+                let total_received = total_received.clone();
+                let total_sum = total_sum.clone();
+                s.spawn(move || {
+                    while total_received.load(Ordering::SeqCst) < SHARDS * MESSAGES {
+                        let mut guard = rx.read_buffer();
+                        // consume only half of the batches, the rest stays in the channel
+                        let count = guard.len().div_ceil(2);
+                        let sum: usize = guard[..count].iter().sum();
+                        guard.advance(count);
+                        drop(guard);
+
+                        total_sum.fetch_add(sum, Ordering::SeqCst);
+                        total_received.fetch_add(count, Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(total_received.load(Ordering::SeqCst), SHARDS * MESSAGES);
+        let n = SHARDS * MESSAGES;
+        assert_eq!(total_sum.load(Ordering::SeqCst), n * (n - 1) / 2);
+    }
+
+    #[test]
+    fn test_read_guard_does_not_block_shard() {
+        let (mut tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let mut rx2 = rx.try_clone().unwrap();
+
+        for i in 0..4 {
+            tx.send(i);
+        }
+        let mut guard = rx.read_buffer();
+        assert_eq!(*guard, [0, 1, 2, 3]);
+
+        // the values after the guard go to the other receiver
+        tx.send(4);
+        assert_eq!(rx2.try_recv(), Some(4));
+        assert_eq!(rx2.try_recv(), None);
+
+        // values claimed before the ones of rx2 are handed back out of order, to any receiver
+        guard.advance(2);
+        drop(guard);
+        assert_eq!(rx2.try_recv(), Some(2));
+        assert_eq!(rx.try_recv(), Some(3));
+
+        // every slot is released
+        for i in 0..8 {
+            tx.try_send(i).unwrap();
+        }
+        assert!(tx.try_send(8).is_err());
+
+        // unconsumed values are handed back when no others were claimed after them
+        let guard = rx.read_buffer();
+        assert_eq!(*guard, [0, 1, 2]);
+        drop(guard);
+        assert_eq!(rx2.try_recv(), Some(0));
+    }
+
+    #[test]
+    fn test_dropped_receiver_hands_back_values() {
+        use alloc_crate::string::{String, ToString};
+
+        let (mut tx, mut rx) =
+            channel::<String>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let mut rx2 = rx.try_clone().unwrap();
+
+        for i in 0..4 {
+            tx.send(i.to_string());
+        }
+        let mut guard = rx.read_buffer();
+        assert_eq!(guard.len(), 4);
+        tx.send(4.to_string());
+        assert_eq!(rx2.try_recv().as_deref(), Some("4"));
+
+        guard.discard(1);
+        drop(guard);
+        drop(rx);
+        assert!(rx2.try_iter().eq(["1", "2", "3"]));
+
+        // nothing keeps the sender from reusing the slots
+        for i in 0..8 {
+            tx.try_send(i.to_string()).unwrap();
+        }
+        assert!(rx2.try_iter().map(|s| s.parse::<usize>().unwrap()).eq(0..8));
+    }
+
+    #[test]
+    fn test_returned_values_dropped_once() {
+        use std::sync::Arc;
+
+        let item = Arc::new(());
+        {
+            let (mut tx, mut rx) =
+                channel::<Arc<()>>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+            let mut rx2 = rx.try_clone().unwrap();
+            for _ in 0..6 {
+                tx.send(item.clone());
+            }
+
+            // rx2 releases a value after the ones handed back by rx
+            let mut guard = rx.read_buffer();
+            assert_eq!(guard.len(), 6);
+            tx.send(item.clone());
+            tx.send(item.clone());
+            drop(rx2.try_recv());
+            guard.discard(1);
+            drop(guard);
+            assert_eq!(Arc::strong_count(&item), 7);
+        }
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
     #[test]
     fn test_drop_full_capacity() {
         use std::sync::Arc;
@@ -318,8 +570,8 @@ mod test {
                     while sent < TOTAL_ITEMS_PER_THREAD {
                         let buffer = tx.write_buffer();
                         let batch_size = buffer.len().min(TOTAL_ITEMS_PER_THREAD - sent);
-                        for i in 0..batch_size {
-                            buffer[i].write(thread_id * 10000 + sent + i);
+                        for (i, slot) in buffer.iter_mut().take(batch_size).enumerate() {
+                            slot.write(thread_id * 10000 + sent + i);
                         }
                         unsafe { tx.commit(batch_size) };
                         sent += batch_size;
@@ -333,8 +585,8 @@ mod test {
                 while sent < TOTAL_ITEMS_PER_THREAD {
                     let buffer = tx.write_buffer();
                     let batch_size = buffer.len().min(TOTAL_ITEMS_PER_THREAD - sent);
-                    for i in 0..batch_size {
-                        buffer[i].write(thread_id * 10000 + sent + i);
+                    for (i, slot) in buffer.iter_mut().take(batch_size).enumerate() {
+                        slot.write(thread_id * 10000 + sent + i);
                     }
                     unsafe { tx.commit(batch_size) };
                     sent += batch_size;
@@ -1,86 +1,254 @@
 use core::ptr::{self, NonNull};
 
 use crate::{
-    Backoff, Box,
+    Allocator, Backoff, Box, Global,
     padded::Padded,
     spsc::{self, shards::ShardsPtr},
-    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    sync::atomic::{self, AtomicBool, AtomicUsize, Ordering},
 };
 
-type Lock = Padded<AtomicBool>;
+/// The consuming side of a shard, shared by all the receivers.
+///
+/// Receivers claim disjoint ranges of the shard by moving `claimed` forward with a CAS, and release
+/// them once they are done with the values. The head of the shard, which its sender waits on, then
+/// moves over the released values in order. A range held by a slow [`ReadGuard`] only keeps the
+/// sender from reusing its slots, other receivers keep claiming the values after it.
+///
+/// Values a guard leaves unconsumed go back to `claimed` if nothing was claimed after them.
+/// Otherwise they are flagged in `returned`, and claimed from there first by any receiver.
+struct Cursor {
+    /// The next index to claim, the head of the shard trails it.
+    claimed: Padded<AtomicUsize>,
+    /// Whether the value of each slot is released, as the parity of the lap it was released in.
+    released: Box<[AtomicBool]>,
+    /// Whether the value of each slot was handed back, it is cleared again before the value is
+    /// released so the flag doesn't need the lap.
+    returned: Box<[AtomicBool]>,
+    /// The number of flags set in `returned`, so that claiming only scans them when some are.
+    returned_len: AtomicUsize,
+}
+
+impl Cursor {
+    fn new(capacity: usize) -> Self {
+        let mut released = Box::new_uninit_slice(capacity);
+        let mut returned = Box::new_uninit_slice(capacity);
+        for (released, returned) in released.iter_mut().zip(returned.iter_mut()) {
+            // released in the lap before the first one
+            released.write(AtomicBool::new(false));
+            returned.write(AtomicBool::new(false));
+        }
+
+        Self {
+            claimed: Padded::new(AtomicUsize::new(0)),
+            released: unsafe { released.assume_init() },
+            returned: unsafe { returned.assume_init() },
+            returned_len: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline(always)]
+    fn lap_flag(index: usize, mask: usize) -> bool {
+        (index >> mask.count_ones()) & 1 == 0
+    }
+
+    #[inline(always)]
+    fn is_released(&self, index: usize, mask: usize) -> bool {
+        self.released[index & mask].load(Ordering::Acquire) == Self::lap_flag(index, mask)
+    }
+
+    #[inline(always)]
+    fn release(&self, start: usize, end: usize, mask: usize) {
+        let mut index = start;
+        while index != end {
+            self.released[index & mask].store(Self::lap_flag(index, mask), Ordering::Release);
+            index = index.wrapping_add(1);
+        }
+    }
+
+    /// Hands `start..end` back for any receiver to claim.
+    #[inline(always)]
+    fn hand_back(&self, start: usize, end: usize, mask: usize) {
+        let mut index = start;
+        while index != end {
+            self.returned[index & mask].store(true, Ordering::Release);
+            index = index.wrapping_add(1);
+        }
+        self.returned_len
+            .fetch_add(end.wrapping_sub(start), Ordering::Release);
+    }
+
+    /// Claims the value in the slot of `index` if it was handed back.
+    #[inline(always)]
+    fn take_returned(&self, index: usize, mask: usize) -> bool {
+        let flag = &self.returned[index & mask];
+        flag.load(Ordering::Relaxed)
+            && flag
+                .compare_exchange(true, false, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+    }
+}
+
+/// A range of values claimed by a receiver but not consumed yet.
+struct Claim {
+    shard: usize,
+    start: usize,
+    end: usize,
+}
 
 /// A guard that provides read access to a batch of elements from the channel.
 ///
-/// When the guard is dropped, the elements are marked as consumed in the channel.
-pub struct ReadGuard<'a, T> {
-    receiver: &'a mut Receiver<T>,
+/// The elements can be looked at through the guard, and either moved out of it with
+/// [`ReadGuard::drain`] or by iterating over it, or dropped with [`ReadGuard::discard`]. When the
+/// guard is dropped, the consumed elements are removed from the channel, the others stay in it.
+pub struct ReadGuard<'a, T, A: Allocator + Clone = Global> {
+    receiver: &'a mut Receiver<T, A>,
     data: NonNull<[T]>,
+    shard: usize,
+    start: usize,
     consumed: usize,
 }
 
-impl<'a, T> core::ops::Deref for ReadGuard<'a, T> {
+impl<'a, T, A: Allocator + Clone> core::ops::Deref for ReadGuard<'a, T, A> {
     type Target = [T];
     fn deref(&self) -> &Self::Target {
-        unsafe { self.data.as_ref() }
+        // the consumed elements are dropped or moved out already
+        unsafe { &self.data.as_ref()[self.consumed..] }
     }
 }
 
-impl<'a, T> Drop for ReadGuard<'a, T> {
+impl<'a, T, A: Allocator + Clone> Drop for ReadGuard<'a, T, A> {
     fn drop(&mut self) {
-        let slice = unsafe { self.data.as_ref() };
-        if !slice.is_empty() {
-            unsafe {
-                self.receiver.advance(self.consumed);
-            }
+        if !self.data.is_empty() {
+            let claim = Claim {
+                shard: self.shard,
+                start: self.start,
+                end: self.start.wrapping_add(self.data.len()),
+            };
+            self.receiver.finish(claim, self.consumed);
         }
     }
 }
 
-impl<'a, T> ReadGuard<'a, T> {
-    /// Marks `len` elements as consumed.
+impl<'a, T, A: Allocator + Clone> ReadGuard<'a, T, A> {
+    /// Marks the first `len` elements as consumed, without dropping them.
+    ///
+    /// These elements will be removed from the channel when the guard is dropped. This suits
+    /// `Copy` data and elements moved out of the guard with [`ptr::read`], elements that need
+    /// dropping are leaked. Use [`ReadGuard::discard`] for those instead.
+    ///
+    /// # Panics
     ///
-    /// These elements will be removed from the channel when the guard is dropped.
+    /// Panics if `len` is greater than the number of elements left in the guard.
     pub fn advance(&mut self, len: usize) {
-        debug_assert!(
-            self.consumed + len <= self.len(),
-            "advancing beyond buffer length"
-        );
+        assert!(len <= self.len(), "advancing beyond buffer length");
         self.consumed += len;
     }
+
+    /// Drops the first `len` elements, marking them as consumed.
+    ///
+    /// Same as [`ReadGuard::advance`], for elements that need to be dropped, like `String` or
+    /// `Box`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is greater than the number of elements left in the guard.
+    pub fn discard(&mut self, len: usize) {
+        assert!(len <= self.len(), "discarding beyond buffer length");
+
+        let ptr = unsafe { self.data.cast::<T>().add(self.consumed) };
+        // consumed first, so that a panicking drop doesn't leave them to be dropped again
+        self.consumed += len;
+        // SAFETY: not consumed yet, and the guard can't give out references to them anymore
+        unsafe { NonNull::slice_from_raw_parts(ptr, len).drop_in_place() };
+    }
+
+    /// Returns an iterator moving the elements out of the guard.
+    ///
+    /// The elements moved out are marked as consumed. Elements it didn't get to because it was
+    /// dropped early stay in the guard.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.take())
+    }
+
+    /// Moves the next element out of the guard, marking it as consumed.
+    #[inline(always)]
+    fn take(&mut self) -> Option<T> {
+        if self.consumed == self.data.len() {
+            return None;
+        }
+
+        // SAFETY: not consumed yet, so the element is initialised and not read by anyone else
+        let ret = unsafe { self.data.cast::<T>().add(self.consumed).read() };
+        self.consumed += 1;
+        Some(ret)
+    }
 }
 
+impl<'a, T, A: Allocator + Clone> IntoIterator for ReadGuard<'a, T, A> {
+    type Item = T;
+    type IntoIter = IntoIter<'a, T, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { guard: self }
+    }
+}
+
+/// An iterator moving the elements out of a [`ReadGuard`].
+///
+/// Elements it didn't get to when it is dropped stay in the channel.
+pub struct IntoIter<'a, T, A: Allocator + Clone = Global> {
+    guard: ReadGuard<'a, T, A>,
+}
+
+impl<'a, T, A: Allocator + Clone> Iterator for IntoIter<'a, T, A> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.guard.take()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.guard.len(), Some(self.guard.len()))
+    }
+}
+
+impl<'a, T, A: Allocator + Clone> ExactSizeIterator for IntoIter<'a, T, A> {}
+
 /// The receiving half of a sharded MPMC channel.
 ///
-/// The receiver attempts to read from shards in a round-robin fashion.
-pub struct Receiver<T> {
-    receivers: Box<[spsc::Receiver<T>]>,
-    locks: NonNull<Lock>,
+/// The receiver attempts to read from shards in a round-robin fashion. Receivers never lock a
+/// shard: each one claims values with a CAS, so a receiver holding a [`ReadGuard`] doesn't keep
+/// the others from reading the rest of the shard.
+pub struct Receiver<T, A: Allocator + Clone = Global> {
+    queues: Box<[spsc::QueuePtr<T, A>]>,
+    cursors: NonNull<Cursor>,
     alive_receivers: NonNull<AtomicUsize>,
-    shards: ShardsPtr<T>,
+    shards: ShardsPtr<T, A>,
     max_shards: usize,
     next_shard: usize,
 }
 
-impl<T> Receiver<T> {
-    pub(super) fn new(shards: ShardsPtr<T>, max_shards: usize) -> Self {
-        let mut locks = Box::<[Lock]>::new_uninit_slice(max_shards);
-        let mut receivers = Box::new_uninit_slice(max_shards);
+impl<T, A: Allocator + Clone> Receiver<T, A> {
+    pub(super) fn new(shards: ShardsPtr<T, A>, max_shards: usize) -> Self {
+        let mut cursors = Box::<[Cursor]>::new_uninit_slice(max_shards);
+        let mut queues = Box::new_uninit_slice(max_shards);
 
         for i in 0..max_shards {
             let shard = shards.clone_queue_ptr(i);
-            receivers[i].write(spsc::Receiver::new(shard));
-
-            locks[i].write(Padded::new(AtomicBool::new(false)));
+            shard.receivers().fetch_add(1, Ordering::AcqRel);
+            cursors[i].write(Cursor::new(shard.capacity()));
+            queues[i].write(shard);
         }
 
-        let locks = unsafe { NonNull::new_unchecked(Box::into_raw(locks.assume_init())) }.cast();
+        let cursors =
+            unsafe { NonNull::new_unchecked(Box::into_raw(cursors.assume_init())) }.cast();
 
         let alive_receivers_ptr = Box::into_raw(Box::new(AtomicUsize::new(1)));
         let alive_receivers = unsafe { NonNull::new_unchecked(alive_receivers_ptr) };
 
         Self {
-            receivers: unsafe { receivers.assume_init() },
-            locks,
+            queues: unsafe { queues.assume_init() },
+            cursors,
             alive_receivers,
             shards,
             max_shards,
@@ -98,21 +266,40 @@ impl<T> Receiver<T> {
             return None;
         }
 
-        let mut receivers = Box::new_uninit_slice(self.max_shards);
-        for i in 0..self.max_shards {
-            receivers[i].write(unsafe { self.receivers[i].clone_via_ptr() });
+        for queue in &self.queues {
+            queue.receivers().fetch_add(1, Ordering::AcqRel);
         }
 
         Some(Self {
-            receivers: unsafe { receivers.assume_init() },
+            queues: self.queues.clone(),
             alive_receivers: self.alive_receivers,
             shards: self.shards.clone(),
-            locks: self.locks,
+            cursors: self.cursors,
             max_shards: self.max_shards,
             next_shard: 0,
         })
     }
 
+    /// Closes the channel, so that sending fails from then on, without dropping the values
+    /// already in it.
+    ///
+    /// The values can still be received, by this receiver and its clones alike.
+    pub fn close(&mut self) {
+        for queue in &self.queues {
+            queue.close();
+        }
+    }
+
+    /// Returns `true` if all the senders were dropped, or if the channel was closed with
+    /// [`Receiver::close`].
+    ///
+    /// The values sent before can still be received.
+    pub fn is_closed(&self) -> bool {
+        self.queues.iter().all(|queue| {
+            queue.senders().load(Ordering::Acquire) == 0 || queue.closed().load(Ordering::Acquire)
+        })
+    }
+
     /// Receives a value from the channel.
     ///
     /// This method will block (spin) until a value is available in any of the shards.
@@ -128,20 +315,18 @@ impl<T> Receiver<T> {
 
     /// Attempts to receive a value from the channel without blocking.
     ///
-    /// Returns `Some(value)` if a value was received, or `None` if all shards are empty or locked.
+    /// Returns `Some(value)` if a value was received, or `None` if all shards are empty.
     pub fn try_recv(&mut self) -> Option<T> {
         let start = self.next_shard;
         loop {
             let idx = self.next_shard;
 
-            if !self.receivers[idx].is_empty() && self.try_lock(idx) {
-                self.receivers[idx].refresh_head();
-                let ret = self.receivers[idx].try_recv();
-                unsafe { self.unlock(idx) };
-
-                if let Some(v) = ret {
-                    return Some(v);
-                }
+            if let Some(claim) = self.claim(idx, 1) {
+                // SAFETY: the value was claimed by this receiver, so no other one reads it, and the
+                //         sender can't overwrite it before it is released
+                let ret = unsafe { self.queues[idx].get(claim.start) };
+                self.release(idx, claim.start, claim.end);
+                return Some(ret);
             }
 
             self.next_shard += 1;
@@ -155,28 +340,53 @@ impl<T> Receiver<T> {
         }
     }
 
+    /// Returns an iterator receiving values without blocking, until all shards are empty.
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.try_recv())
+    }
+
+    /// Returns an iterator receiving values, blocking for each one until all the senders are
+    /// dropped and every shard is empty.
+    ///
+    /// It also ends once the channel is closed with [`Receiver::close`] and emptied.
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        crate::iter::blocking(self, Self::try_recv, Self::is_closed)
+    }
+
+    /// Returns an iterator receiving the values available in the shards when it is called.
+    ///
+    /// Unlike [`Receiver::try_iter`], it ends even if the senders keep sending. Values it didn't
+    /// get to because it was dropped early stay in the channel.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        let mut len = 0;
+        for (shard, queue) in self.queues.iter().enumerate() {
+            let cursor = self.cursor(shard);
+            // loaded first, the tail can only be ahead of it
+            let claimed = cursor.claimed.value.load(Ordering::Acquire);
+            len += queue.tail().load(Ordering::Acquire).wrapping_sub(claimed);
+            len = len.wrapping_add(cursor.returned_len.load(Ordering::Acquire));
+        }
+        self.try_iter().take(len)
+    }
+
+    /// Drops all the values available in the shards.
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+
     /// Returns a [`ReadGuard`] providing read access to a batch of elements from the channel.
     ///
+    /// The elements of the batch are claimed by this receiver until the guard is dropped. Other
+    /// receivers can still read the elements after them, including from the same shard.
+    ///
     /// If no elements are available, an empty [`ReadGuard`] is returned.
-    pub fn read_buffer(&mut self) -> ReadGuard<'_, T> {
+    pub fn read_buffer(&mut self) -> ReadGuard<'_, T, A> {
         let start = self.next_shard;
         loop {
             let idx = self.next_shard;
 
-            if !self.receivers[idx].is_empty() && self.try_lock(idx) {
-                let receiver_ptr = &mut self.receivers[idx] as *mut spsc::Receiver<T>;
-                unsafe { (*receiver_ptr).refresh_head() };
-                let slice = unsafe { (*receiver_ptr).read_buffer() };
-
-                if !slice.is_empty() {
-                    return ReadGuard {
-                        receiver: self,
-                        data: NonNull::from_ref(slice),
-                        consumed: 0,
-                    };
-                } else {
-                    unsafe { self.unlock(idx) };
-                }
+            if let Some(claim) = self.claim(idx, usize::MAX) {
+                return self.guard(claim);
             }
 
             self.next_shard += 1;
@@ -188,52 +398,185 @@ impl<T> Receiver<T> {
                 return ReadGuard {
                     receiver: self,
                     data: NonNull::from_ref(&[]),
+                    shard: 0,
+                    start: 0,
                     consumed: 0,
                 };
             }
         }
     }
 
-    /// # Safety
-    ///
-    /// - `self.read_buffer` must've been called before this, and it should've returned a non-empty
-    ///   slice
-    /// - this should be called **only once** after `self.read_buffer` returned a non-empty slice.
-    unsafe fn advance(&mut self, len: usize) {
-        // SAFETY: caller guarantees that read_buffer was called before, and it returned a buffer
-        //         which was NOT empty
-        unsafe {
-            self.receivers[self.next_shard].advance(len);
-            self.unlock(self.next_shard);
+    fn guard(&mut self, claim: Claim) -> ReadGuard<'_, T, A> {
+        let len = claim.end.wrapping_sub(claim.start);
+        // SAFETY: claims never wrap around the end of the buffer
+        let ptr = unsafe { self.queues[claim.shard].at(claim.start) };
+
+        ReadGuard {
+            data: NonNull::slice_from_raw_parts(ptr, len),
+            shard: claim.shard,
+            start: claim.start,
+            consumed: 0,
+            receiver: self,
         }
     }
 
     #[inline(always)]
-    fn shard_lock(&self, shard: usize) -> &AtomicBool {
-        unsafe { self.locks.add(shard).cast::<AtomicBool>().as_ref() }
+    fn cursor(&self, shard: usize) -> &Cursor {
+        unsafe { self.cursors.add(shard).as_ref() }
     }
 
-    #[inline(always)]
-    fn try_lock(&self, shard: usize) -> bool {
-        self.shard_lock(shard)
-            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
-            .is_ok()
+    /// Claims up to `max` values of `shard`, which are contiguous in its buffer.
+    ///
+    /// The values handed back by a [`ReadGuard`] are claimed first.
+    fn claim(&self, shard: usize, max: usize) -> Option<Claim> {
+        if let Some(claim) = self.claim_returned(shard, max) {
+            return Some(claim);
+        }
+
+        let queue = &self.queues[shard];
+        let claimed = &self.cursor(shard).claimed.value;
+
+        // Acquire pairs with the Release of other receivers, so that the tail loaded below is at
+        // least as recent as the one the index was claimed against
+        let mut start = claimed.load(Ordering::Acquire);
+        loop {
+            let available = queue.tail().load(Ordering::Acquire).wrapping_sub(start);
+            if available == 0 {
+                return None;
+            }
+
+            let contiguous = queue.capacity() - (start & queue.mask());
+            let end = start.wrapping_add(available.min(contiguous).min(max));
+            match claimed.compare_exchange_weak(start, end, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return Some(Claim { shard, start, end }),
+                Err(actual) => start = actual,
+            }
+        }
     }
 
-    /// # Safety
-    /// Only call this if `try_lock` returned `true` earlier, and this is the only `unlock` after
-    /// that.
-    #[inline(always)]
-    unsafe fn unlock(&self, shard: usize) {
-        self.shard_lock(shard).store(false, Ordering::Release);
+    /// Claims up to `max` values of `shard` handed back by a [`ReadGuard`], which are contiguous in
+    /// its buffer.
+    fn claim_returned(&self, shard: usize, max: usize) -> Option<Claim> {
+        let queue = &self.queues[shard];
+        let cursor = self.cursor(shard);
+        if cursor.returned_len.load(Ordering::Acquire) == 0 {
+            return None;
+        }
+
+        let mask = queue.mask();
+        let claimed = cursor.claimed.value.load(Ordering::Acquire);
+        let mut index = queue.head().load(Ordering::Acquire);
+        while index != claimed {
+            if cursor.take_returned(index, mask) {
+                // the head may have moved on while scanning, and the slot been handed back in a
+                // later lap. Now that the value is claimed, the head stays less than a lap behind
+                let head = queue.head().load(Ordering::Acquire);
+                let start = head.wrapping_add(index.wrapping_sub(head) & mask);
+                let limit = head.wrapping_add(queue.capacity());
+
+                let mut end = start.wrapping_add(1);
+                while end.wrapping_sub(start) < max
+                    && end & mask != 0
+                    && end != limit
+                    && cursor.take_returned(end, mask)
+                {
+                    end = end.wrapping_add(1);
+                }
+
+                cursor
+                    .returned_len
+                    .fetch_sub(end.wrapping_sub(start), Ordering::Relaxed);
+                return Some(Claim { shard, start, end });
+            }
+            index = index.wrapping_add(1);
+        }
+
+        None
+    }
+
+    /// Releases `start..end` of `shard`, whose values were moved out, then moves the head of the
+    /// shard over all the values released so far.
+    fn release(&self, shard: usize, start: usize, end: usize) {
+        let queue = &self.queues[shard];
+        let cursor = self.cursor(shard);
+        let mask = queue.mask();
+        cursor.release(start, end, mask);
+
+        // pairs with the fence of any receiver releasing values right before these: either it sees
+        // the values released here, or the scan below sees its values
+        atomic::fence(Ordering::SeqCst);
+
+        let head = queue.head();
+        let mut current = head.load(Ordering::Acquire);
+        loop {
+            let mut next = current;
+            while cursor.is_released(next, mask) {
+                next = next.wrapping_add(1);
+            }
+            if next == current {
+                return;
+            }
+
+            match head.compare_exchange(current, next, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => current = next,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Releases the first `consumed` values of a claim of a [`ReadGuard`], and gives the others
+    /// back to the shard for any receiver to claim.
+    fn finish(&self, claim: Claim, consumed: usize) {
+        let done = claim.start.wrapping_add(consumed);
+        if done != claim.end {
+            let cursor = self.cursor(claim.shard);
+            if cursor
+                .claimed
+                .value
+                .compare_exchange(claim.end, done, Ordering::AcqRel, Ordering::Relaxed)
+                .is_err()
+            {
+                // other values were claimed after these, which can't go back to `claimed`
+                let mask = self.queues[claim.shard].mask();
+                cursor.hand_back(done, claim.end, mask);
+            }
+        }
+
+        if consumed != 0 {
+            self.release(claim.shard, claim.start, done);
+        }
     }
 }
 
-impl<T> Drop for Receiver<T> {
+impl<T, A: Allocator + Clone> Drop for Receiver<T, A> {
     fn drop(&mut self) {
-        unsafe {
-            if self.alive_receivers.as_ref().fetch_sub(1, Ordering::AcqRel) == 1 {
-                let slice_ptr = ptr::slice_from_raw_parts_mut(self.locks.as_ptr(), self.max_shards);
+        let last = unsafe { self.alive_receivers.as_ref() }.fetch_sub(1, Ordering::AcqRel) == 1;
+        if last {
+            // the values handed back keep the head from moving over the values released after
+            // them, which the shards would then drop again
+            for shard in 0..self.max_shards {
+                while let Some(claim) = self.claim_returned(shard, usize::MAX) {
+                    let mut index = claim.start;
+                    while index != claim.end {
+                        unsafe { self.queues[shard].at(index).drop_in_place() };
+                        index = index.wrapping_add(1);
+                    }
+                    self.release(shard, claim.start, claim.end);
+                }
+            }
+        }
+
+        // nobody is going to receive the values sent from now on
+        for queue in &self.queues {
+            if queue.receivers().fetch_sub(1, Ordering::AcqRel) == 1 {
+                queue.close();
+            }
+        }
+
+        if last {
+            unsafe {
+                let slice_ptr =
+                    ptr::slice_from_raw_parts_mut(self.cursors.as_ptr(), self.max_shards);
                 _ = Box::from_raw(slice_ptr);
                 _ = Box::from_raw(self.alive_receivers.as_ptr());
             }
@@ -241,4 +584,4 @@ impl<T> Drop for Receiver<T> {
     }
 }
 
-unsafe impl<T> Send for Receiver<T> {}
+unsafe impl<T: Send, A: Allocator + Clone + Send> Send for Receiver<T, A> {}
@@ -1,86 +1,90 @@
-use core::{mem::MaybeUninit, num::NonZeroUsize, ptr::NonNull};
+use core::{mem::MaybeUninit, num::NonZeroUsize};
 
 use crate::{
-    Box,
+    Allocator, Global,
     spsc::{self, shards::ShardsPtr},
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::atomic::Ordering,
 };
 
 /// The sending half of a sharded MPMC channel.
 ///
 /// Each sender is bound to a specific shard. Cloning a sender will attempt to bind the new
 /// instance to a different, unused shard.
-pub struct Sender<T> {
-    inner: spsc::Sender<T>,
-    shards: ShardsPtr<T>,
-    num_senders: NonNull<AtomicUsize>,
-    alive_senders: NonNull<AtomicUsize>,
+pub struct Sender<T, A: Allocator + Clone = Global> {
+    inner: spsc::Sender<T, A>,
+    shards: ShardsPtr<T, A>,
     max_shards: usize,
 }
 
-impl<T> Sender<T> {
+impl<T, A: Allocator + Clone> Sender<T, A> {
     /// Attempts to clone the sender.
     ///
     /// Returns `Some(Sender)` if there is an available shard to bind to, otherwise returns `None`.
     pub fn try_clone(&self) -> Option<Self> {
-        unsafe {
-            Self::init(
-                self.shards.clone(),
-                self.max_shards,
-                self.num_senders,
-                self.alive_senders,
-            )
-        }
+        self.shards.senders().fetch_add(1, Ordering::AcqRel);
+        Self::init(self.shards.clone(), self.max_shards)
     }
 
-    pub(super) fn new(shards: ShardsPtr<T>, max_shards: NonZeroUsize) -> Self {
-        let num_senders_ptr = Box::into_raw(Box::new(AtomicUsize::new(0)));
-        let alive_senders_ptr = Box::into_raw(Box::new(AtomicUsize::new(0)));
-        unsafe {
-            let num_senders = NonNull::new_unchecked(num_senders_ptr);
-            let alive_senders = NonNull::new_unchecked(alive_senders_ptr);
-            Self::init(shards, max_shards.get(), num_senders, alive_senders).unwrap_unchecked()
+    /// Creates a [`WeakSender`] for this channel.
+    ///
+    /// The weak handle isn't bound to a shard and doesn't keep the channel open: once all the
+    /// [`Sender`]s are dropped, the channel is disconnected even if weak handles remain.
+    pub fn downgrade(&self) -> WeakSender<T, A> {
+        WeakSender {
+            shards: self.shards.clone(),
+            max_shards: self.max_shards,
         }
     }
 
-    unsafe fn init(
-        shards: ShardsPtr<T>,
-        max_shards: usize,
-        num_senders: NonNull<AtomicUsize>,
-        alive_senders: NonNull<AtomicUsize>,
-    ) -> Option<Self> {
-        let num_senders_ref = unsafe { num_senders.as_ref() };
-        let next_shard = num_senders_ref.fetch_add(1, Ordering::Relaxed);
+    pub(super) fn new(shards: ShardsPtr<T, A>, max_shards: NonZeroUsize) -> Self {
+        shards.senders().fetch_add(1, Ordering::AcqRel);
+        unsafe { Self::init(shards, max_shards.get()).unwrap_unchecked() }
+    }
+
+    /// Binds a new sender to the next unused shard, the caller must already have counted it as
+    /// alive.
+    fn init(shards: ShardsPtr<T, A>, max_shards: usize) -> Option<Self> {
+        let claimed = shards.claimed();
+        let next_shard = claimed.fetch_add(1, Ordering::Relaxed);
         if next_shard >= max_shards {
-            num_senders_ref.store(max_shards, Ordering::Relaxed);
+            claimed.store(max_shards, Ordering::Relaxed);
+            shards.senders().fetch_sub(1, Ordering::AcqRel);
             return None;
         }
 
-        // AcqRel because can't have this before num_senders is done
-        unsafe { alive_senders.as_ref() }.fetch_add(1, Ordering::AcqRel);
-
         let shard_ptr = shards.clone_queue_ptr(next_shard);
         let inner = spsc::Sender::new(shard_ptr);
 
         Some(Self {
             inner,
             shards,
-            num_senders,
-            alive_senders,
             max_shards,
         })
     }
 
+    /// Returns `true` if a receiver closed the channel with [`Receiver::close`], or if all
+    /// the receivers were dropped.
+    ///
+    /// Sending fails from then on. This can be used to cancel the work producing the values
+    /// early.
+    ///
+    /// [`Receiver::close`]: super::Receiver::close
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
     /// Sends a value into the channel.
     ///
-    /// This method will block (spin) until there is space in the shard's queue.
+    /// This method will block (spin) until there is space in the shard's queue. If the channel is
+    /// closed, the value is dropped and this returns right away.
     pub fn send(&mut self, value: T) {
         self.inner.send(value)
     }
 
     /// Attempts to send a value into the channel without blocking.
     ///
-    /// Returns `Ok(())` if the value was sent, or `Err(value)` if the shard's queue is full.
+    /// Returns `Ok(())` if the value was sent, or `Err(value)` if the shard's queue is full or the
+    /// channel is closed.
     pub fn try_send(&mut self, value: T) -> Result<(), T> {
         self.inner.try_send(value)
     }
@@ -100,15 +104,58 @@ impl<T> Sender<T> {
     }
 }
 
-impl<T> Drop for Sender<T> {
+impl<T, A: Allocator + Clone> Drop for Sender<T, A> {
     fn drop(&mut self) {
-        unsafe {
-            if self.alive_senders.as_ref().fetch_sub(1, Ordering::AcqRel) == 1 {
-                _ = Box::from_raw(self.num_senders.as_ptr());
-                _ = Box::from_raw(self.alive_senders.as_ptr());
+        self.shards.senders().fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+unsafe impl<T: Send, A: Allocator + Clone + Send> Send for Sender<T, A> {}
+
+/// A handle to the channel that doesn't keep it open, created with [`Sender::downgrade`].
+///
+/// Useful when a consumer needs a way to send to its own channel without preventing it from
+/// being disconnected, as with an actor holding a handle to its mailbox.
+pub struct WeakSender<T, A: Allocator + Clone = Global> {
+    shards: ShardsPtr<T, A>,
+    max_shards: usize,
+}
+
+impl<T, A: Allocator + Clone> WeakSender<T, A> {
+    /// Attempts to turn this into a [`Sender`] bound to an unused shard.
+    ///
+    /// Returns `None` if all the senders were already dropped, or if there is no shard left to
+    /// bind to. Shards aren't reused, so a shard freed by a dropped sender can't be taken again.
+    pub fn upgrade(&self) -> Option<Sender<T, A>> {
+        let senders = self.shards.senders();
+        let mut count = senders.load(Ordering::Relaxed);
+        loop {
+            // once the count reached 0 the channel is disconnected for good
+            if count == 0 {
+                return None;
             }
+            match senders.compare_exchange_weak(
+                count,
+                count + 1,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(cur) => count = cur,
+            }
+        }
+
+        Sender::init(self.shards.clone(), self.max_shards)
+    }
+}
+
+impl<T, A: Allocator + Clone> Clone for WeakSender<T, A> {
+    fn clone(&self) -> Self {
+        Self {
+            shards: self.shards.clone(),
+            max_shards: self.max_shards,
         }
     }
 }
 
-unsafe impl<T> Send for Sender<T> {}
+unsafe impl<T: Send, A: Allocator + Clone + Send> Send for WeakSender<T, A> {}
@@ -1,14 +1,13 @@
 use core::{
     marker::PhantomData,
     mem::{align_of, size_of},
-    num::NonZeroUsize,
     ptr::NonNull,
 };
 
 use crate::{
-    alloc,
-    atomic::{AtomicUsize, Ordering},
-    cell::{Cell, CellPtr},
+    Allocator, Capacity, Dynamic, Global, alloc, allocator,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    cell::{Cell, CellLayout, CellPtr, PaddedCells},
     padded::Padded,
 };
 
@@ -19,48 +18,51 @@ use crate::{
 struct Queue {
     tail: Padded<AtomicUsize>,
     rc: AtomicUsize,
+    /// The number of senders and receivers using the queue.
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+    /// Set once the receivers closed the queue, sending fails from then on.
+    closed: AtomicBool,
 }
 
-pub(crate) struct QueuePtr<T> {
+pub(crate) struct QueuePtr<
+    T,
+    A: Allocator = Global,
+    C: Capacity = Dynamic,
+    L: CellLayout = PaddedCells,
+> {
     ptr: NonNull<Queue>,
     buffer: NonNull<Cell<T>>,
-    pub(crate) size: usize,
-    pub(crate) mask: usize,
-    pub(crate) capacity: usize,
-    _marker: PhantomData<T>,
+    cap: C,
+    alloc: A,
+    _marker: PhantomData<(T, L)>,
 }
 
-impl<T> Clone for QueuePtr<T> {
+impl<T, A: Allocator + Clone, C: Capacity, L: CellLayout> Clone for QueuePtr<T, A, C, L> {
     fn clone(&self) -> Self {
         let rc = unsafe { _field!(Queue, self.ptr, rc, AtomicUsize).as_ref() };
         rc.fetch_add(1, Ordering::AcqRel);
         Self {
             ptr: self.ptr,
             buffer: self.buffer,
-            size: self.size,
-            mask: self.mask,
-            capacity: self.capacity,
+            cap: self.cap,
+            alloc: self.alloc.clone(),
             _marker: PhantomData,
         }
     }
 }
 
-impl<T> QueuePtr<T> {
-    pub(crate) fn with_size(size: NonZeroUsize) -> Self {
+impl<T, A: Allocator, C: Capacity, L: CellLayout> QueuePtr<T, A, C, L> {
+    pub(crate) fn with_capacity_in(cap: C, alloc: A) -> Self {
         // Allocate exactly capacity + 1 slots (one slot is always empty to distinguish full from empty)
-        let size = size.get();
-        let capacity = size.next_power_of_two();
+        let capacity = cap.capacity();
 
         let (layout, buffer_offset) = Self::layout(capacity);
 
-        // SAFETY: capacity > 0, so layout is non-zero too
-        let ptr = unsafe { alloc::alloc(layout) } as *mut Queue;
-        let Some(ptr) = NonNull::new(ptr) else {
-            alloc::handle_alloc_error(layout);
-        };
+        let ptr = allocator::allocate(&alloc, layout).cast::<Queue>();
 
         // calculate buffer pointer
-        // SAFETY: `ptr` is already checked by NonNull::new above, so this is guaranteed to be
+        // SAFETY: `ptr` is non-null as returned by the allocator, so this is guaranteed to be
         // valid ptr too
         let buffer = unsafe {
             NonNull::new_unchecked(ptr.as_ptr().byte_add(buffer_offset).cast::<Cell<T>>())
@@ -71,60 +73,88 @@ impl<T> QueuePtr<T> {
             ptr.write(Queue {
                 tail: Padded::new(AtomicUsize::new(0)),
                 rc: AtomicUsize::new(1),
+                senders: AtomicUsize::new(0),
+                receivers: AtomicUsize::new(0),
+                closed: AtomicBool::new(false),
             });
         };
 
-        // SAFETY: we just allocated it, and atomics are safe to access without initialisation
-        let buffer_slice = unsafe { core::slice::from_raw_parts_mut(buffer.as_ptr(), capacity) };
-        for (idx, cell) in buffer_slice.iter_mut().enumerate() {
-            cell.epoch.store(idx, Ordering::Relaxed);
-        }
-
-        Self {
+        let queue = Self {
             ptr,
             buffer,
+            alloc,
             _marker: PhantomData,
-            size,
-            capacity,
-            mask: capacity - 1,
+            cap,
+        };
+
+        // atomics are safe to access without initialisation
+        for idx in 0..capacity {
+            queue.at(idx).epoch().store(idx, Ordering::Relaxed);
         }
+
+        queue
     }
 
     fn layout(capacity: usize) -> (alloc::Layout, usize) {
         let header_layout =
             alloc::Layout::from_size_align(size_of::<Queue>(), align_of::<Queue>()).unwrap();
-        let buffer_layout = alloc::Layout::array::<Cell<T>>(capacity).unwrap();
+        let buffer_layout = L::array::<T>(capacity).unwrap();
         header_layout.extend(buffer_layout).unwrap()
     }
 
+    #[inline(always)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.cap.capacity()
+    }
+
+    #[inline(always)]
+    pub(crate) fn mask(&self) -> usize {
+        self.cap.mask()
+    }
+
+    #[inline(always)]
+    pub(crate) fn senders(&self) -> &AtomicUsize {
+        unsafe { _field!(Queue, self.ptr, senders, AtomicUsize).as_ref() }
+    }
+
+    #[inline(always)]
+    pub(crate) fn receivers(&self) -> &AtomicUsize {
+        unsafe { _field!(Queue, self.ptr, receivers, AtomicUsize).as_ref() }
+    }
+
+    #[inline(always)]
+    pub(crate) fn closed(&self) -> &AtomicBool {
+        unsafe { _field!(Queue, self.ptr, closed, AtomicBool).as_ref() }
+    }
+
     #[inline(always)]
     pub(crate) fn tail(&self) -> &AtomicUsize {
         unsafe { _field!(Queue, self.ptr, tail.value, AtomicUsize).as_ref() }
     }
 
     #[inline(always)]
-    pub(crate) fn exact_at(&self, index: usize) -> CellPtr<T> {
-        debug_assert!(index < self.capacity);
+    pub(crate) fn exact_at(&self, slot: usize) -> CellPtr<T> {
+        debug_assert!(slot < self.capacity());
 
-        unsafe { self.buffer.add(index) }.into()
+        unsafe { self.buffer.byte_add(slot * L::stride::<T>()) }.into()
     }
 
     #[inline(always)]
     pub(crate) fn at(&self, index: usize) -> CellPtr<T> {
-        self.exact_at(index & self.mask)
+        self.exact_at(L::slot::<T>(index, self.mask()))
     }
 }
 
-impl<T> Drop for QueuePtr<T> {
+impl<T, A: Allocator, C: Capacity, L: CellLayout> Drop for QueuePtr<T, A, C, L> {
     fn drop(&mut self) {
         let rc = unsafe { _field!(Queue, self.ptr, rc, AtomicUsize).as_ref() };
         if rc.fetch_sub(1, Ordering::AcqRel) == 1 {
-            let (layout, _) = Self::layout(self.capacity);
+            let (layout, _) = Self::layout(self.capacity());
 
             let tail = self.tail().load(Ordering::Relaxed);
 
             if core::mem::needs_drop::<T>() {
-                for i in 1..=self.capacity {
+                for i in 1..=self.capacity() {
                     let idx = tail.wrapping_sub(i);
                     let cell = self.at(idx);
                     if cell.epoch().load(Ordering::Relaxed) == idx.wrapping_add(1) {
@@ -135,7 +165,7 @@ impl<T> Drop for QueuePtr<T> {
 
             unsafe {
                 self.ptr.drop_in_place();
-                alloc::dealloc(self.ptr.cast().as_ptr(), layout);
+                self.alloc.deallocate(self.ptr.cast(), layout);
             }
         }
     }
@@ -1,22 +1,42 @@
-use crate::{atomic::Ordering, mpsc::queue::QueuePtr};
+use crate::{
+    Allocator, Capacity, CellLayout, Dynamic, Global, PaddedCells, atomic::Ordering,
+    mpsc::queue::QueuePtr,
+};
 
 /// The consumer end of the queue.
 ///
 /// This struct is `Send` but not `Sync`. It can be moved to another thread, but cannot be shared
 /// across threads.
-pub struct Receiver<T> {
-    ptr: QueuePtr<T>,
+pub struct Receiver<T, A: Allocator = Global, C: Capacity = Dynamic, L: CellLayout = PaddedCells> {
+    ptr: QueuePtr<T, A, C, L>,
     local_head: usize,
 }
 
-impl<T> Receiver<T> {
-    pub(crate) fn new(queue_ptr: QueuePtr<T>) -> Self {
+impl<T, A: Allocator, C: Capacity, L: CellLayout> Receiver<T, A, C, L> {
+    pub(crate) fn new(queue_ptr: QueuePtr<T, A, C, L>) -> Self {
+        queue_ptr.receivers().fetch_add(1, Ordering::AcqRel);
         Self {
             ptr: queue_ptr,
             local_head: 0,
         }
     }
 
+    /// Closes the queue, so that sending fails from then on, without dropping the values already
+    /// in it.
+    ///
+    /// The values can still be received.
+    pub fn close(&mut self) {
+        self.ptr.closed().store(true, Ordering::Release);
+    }
+
+    /// Returns `true` if all the senders were dropped, or if the queue was closed with
+    /// [`Receiver::close`].
+    ///
+    /// The values sent before can still be received.
+    pub fn is_closed(&self) -> bool {
+        self.ptr.senders().load(Ordering::Acquire) == 0 || self.ptr.closed().load(Ordering::Acquire)
+    }
+
     /// Receives a value from the queue, blocking if necessary.
     ///
     /// This method uses a spin loop to wait for available data in the queue.
@@ -32,7 +52,7 @@ impl<T> Receiver<T> {
 
         let ret = unsafe { cell.get() };
         cell.epoch().store(
-            self.local_head.wrapping_add(self.ptr.capacity),
+            self.local_head.wrapping_add(self.ptr.capacity()),
             Ordering::Release,
         );
 
@@ -57,7 +77,7 @@ impl<T> Receiver<T> {
 
         let ret = unsafe { cell.get() };
         cell.epoch().store(
-            self.local_head.wrapping_add(self.ptr.capacity),
+            self.local_head.wrapping_add(self.ptr.capacity()),
             Ordering::Release,
         );
 
@@ -65,6 +85,79 @@ impl<T> Receiver<T> {
 
         Some(ret)
     }
+
+    /// Returns a reference to the next value in the queue without receiving it.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(&value)` if a value is available.
+    /// * `None` if the queue is empty.
+    pub fn peek(&self) -> Option<&T> {
+        let cell = self.ptr.at(self.local_head);
+        if cell.epoch().load(Ordering::Acquire) < self.local_head.wrapping_add(1) {
+            return None;
+        }
+
+        // SAFETY: the value is published, and only this receiver can take it out of the queue,
+        //         which needs a mutable borrow
+        Some(unsafe { cell.as_ref() })
+    }
+
+    /// Receives the next value in the queue if `f` returns `true` for it, without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(value)` if a value is available and accepted by `f`.
+    /// * `None` if the queue is empty or `f` returned `false`, leaving the value in the queue.
+    pub fn recv_if(&mut self, f: impl FnOnce(&T) -> bool) -> Option<T> {
+        if !f(self.peek()?) {
+            return None;
+        }
+        self.try_recv()
+    }
+
+    /// Returns an iterator receiving values without blocking, until the queue is empty.
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.try_recv())
+    }
+
+    /// Returns an iterator receiving values, blocking for each one until all the senders are
+    /// dropped and the queue is empty.
+    ///
+    /// It also ends once the channel is closed with [`Receiver::close`] and emptied.
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        crate::iter::blocking(self, Self::try_recv, Self::is_closed)
+    }
+
+    /// Returns an iterator receiving the values available in the queue when it is called.
+    ///
+    /// Unlike [`Receiver::try_iter`], it ends even if the senders keep sending. Values it didn't
+    /// get to because it was dropped early stay in the queue.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        let len = self
+            .ptr
+            .tail()
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.local_head);
+        self.try_iter().take(len)
+    }
+
+    /// Drops all the values available in the queue.
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
 }
 
-unsafe impl<T: Send> Send for Receiver<T> {}
+impl<T, A: Allocator, C: Capacity, L: CellLayout> Drop for Receiver<T, A, C, L> {
+    fn drop(&mut self) {
+        // nobody is going to receive the values sent from now on
+        if self.ptr.receivers().fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.ptr.closed().store(true, Ordering::Release);
+        }
+    }
+}
+
+unsafe impl<T: Send, A: Allocator + Send, C: Capacity, L: CellLayout> Send
+    for Receiver<T, A, C, L>
+{
+}
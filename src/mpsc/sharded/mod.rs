@@ -21,11 +21,14 @@
 
 use core::num::NonZeroUsize;
 
-use crate::spsc::shards::ShardsPtr;
+use crate::{Allocator, Global, spsc::shards::ShardsPtr};
 
 mod receiver;
 mod sender;
 
+pub use receiver::Receiver;
+pub use sender::{Sender, WeakSender};
+
 /// Creates a new sharded multi-producer single-consumer channel.
 ///
 /// See the [module-level documentation](self) for more details on performance implications.
@@ -42,13 +45,25 @@ pub fn channel<T>(
     max_shards: NonZeroUsize,
     capacity_per_shard: NonZeroUsize,
 ) -> (sender::Sender<T>, receiver::Receiver<T>) {
+    channel_in(max_shards, capacity_per_shard, Global)
+}
+
+/// Creates a new sharded multi-producer single-consumer channel in the given allocator.
+///
+/// Same as [`channel`], but the shards are allocated with `alloc` instead of the [`Global`]
+/// allocator.
+pub fn channel_in<T, A: Allocator + Clone>(
+    max_shards: NonZeroUsize,
+    capacity_per_shard: NonZeroUsize,
+    alloc: A,
+) -> (sender::Sender<T, A>, receiver::Receiver<T, A>) {
     debug_assert_ne!(max_shards.get(), 0, "number of shards must be > 0");
     debug_assert!(
         max_shards.is_power_of_two(),
         "number of shards must be a power of 2"
     );
 
-    let shards = ShardsPtr::new(max_shards, capacity_per_shard);
+    let shards = ShardsPtr::new(max_shards, capacity_per_shard, alloc);
 
     (
         sender::Sender::new(shards.clone(), max_shards),
@@ -100,6 +115,95 @@ mod test {
         });
     }
 
+    #[test]
+    fn test_is_closed() {
+        let (mut tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let mut tx2 = tx.clone().unwrap();
+        tx.send(0);
+        tx2.send(1);
+        drop(tx);
+        assert!(!rx.is_closed());
+        drop(tx2);
+        assert!(rx.is_closed());
+        assert!(rx.iter().eq([0, 1]));
+
+        let (tx, rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        assert!(!tx.is_closed());
+        drop(rx);
+        assert!(tx.is_closed());
+    }
+
+    #[test]
+    fn test_weak_sender() {
+        let (tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let weak = tx.downgrade();
+        let mut tx2 = weak.clone().upgrade().unwrap();
+        assert!(weak.upgrade().is_none(), "no shard left");
+        tx2.send(1);
+        assert_eq!(rx.try_recv(), Some(1));
+
+        drop(tx);
+        drop(tx2);
+        assert!(rx.is_closed());
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_dropped_shard_not_reused() {
+        let (tx_a, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let mut tx_b = tx_a.clone().unwrap();
+        let weak = tx_b.downgrade();
+        drop(tx_a);
+
+        // the only shard left in use belongs to `tx_b`
+        assert!(weak.upgrade().is_none());
+        assert!(tx_b.clone().is_none());
+
+        tx_b.send(10);
+        tx_b.send(20);
+        assert_eq!(rx.try_recv(), Some(10));
+        assert_eq!(rx.try_recv(), Some(20));
+        assert_eq!(rx.try_recv(), None);
+    }
+
+    #[test]
+    fn test_close() {
+        let (mut tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let mut tx2 = tx.clone().unwrap();
+        tx.send(0);
+        rx.close();
+        assert!(tx.is_closed() && tx2.is_closed() && rx.is_closed());
+        assert_eq!(tx2.try_send(1), Err(1));
+        tx.send(2);
+        assert!(rx.drain().eq([0]));
+    }
+
+    #[test]
+    fn test_drain() {
+        let (tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let mut tx2 = tx.clone().unwrap();
+        let mut tx1 = tx;
+        for i in 0..4 {
+            tx1.send(i);
+            tx2.send(4 + i);
+        }
+
+        let mut drained = rx.drain().collect::<vec::Vec<_>>();
+        drained.sort();
+        assert!(drained.into_iter().eq(0..8));
+
+        tx1.send(8);
+        tx2.send(9);
+        rx.clear();
+        assert!(rx.try_iter().next().is_none());
+    }
+
     #[test]
     fn test_valid_try_sends() {
         let (mut tx, mut rx) =
@@ -174,8 +278,8 @@ mod test {
                     while sent < TOTAL_ITEMS_PER_THREAD {
                         let buffer = tx.write_buffer();
                         let batch_size = buffer.len().min(TOTAL_ITEMS_PER_THREAD - sent);
-                        for i in 0..batch_size {
-                            buffer[i].write(thread_id * 10000 + sent + i);
+                        for (i, slot) in buffer.iter_mut().take(batch_size).enumerate() {
+                            slot.write(thread_id * 10000 + sent + i);
                         }
                         unsafe { tx.commit(batch_size) };
                         sent += batch_size;
@@ -189,8 +293,8 @@ mod test {
                 while sent < TOTAL_ITEMS_PER_THREAD {
                     let buffer = tx.write_buffer();
                     let batch_size = buffer.len().min(TOTAL_ITEMS_PER_THREAD - sent);
-                    for i in 0..batch_size {
-                        buffer[i].write(thread_id * 10000 + sent + i);
+                    for (i, slot) in buffer.iter_mut().take(batch_size).enumerate() {
+                        slot.write(thread_id * 10000 + sent + i);
                     }
                     unsafe { tx.commit(batch_size) };
                     sent += batch_size;
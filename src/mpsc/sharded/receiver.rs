@@ -1,19 +1,19 @@
 use crate::{
-    Backoff, Box,
+    Allocator, Backoff, Box, Global,
     spsc::{self, shards::ShardsPtr},
 };
 
 /// The receiving half of a sharded MPSC channel.
 ///
 /// The receiver attempts to read from shards in a round-robin fashion.
-pub struct Receiver<T> {
-    receivers: Box<[spsc::Receiver<T>]>,
+pub struct Receiver<T, A: Allocator + Clone = Global> {
+    receivers: Box<[spsc::Receiver<T, A>]>,
     max_shards: usize,
     next_shard: usize,
 }
 
-impl<T> Receiver<T> {
-    pub(crate) fn new(shards: ShardsPtr<T>, max_shards: usize) -> Self {
+impl<T, A: Allocator + Clone> Receiver<T, A> {
+    pub(crate) fn new(shards: ShardsPtr<T, A>, max_shards: usize) -> Self {
         let mut receivers = Box::new_uninit_slice(max_shards);
 
         for i in 0..max_shards {
@@ -28,6 +28,24 @@ impl<T> Receiver<T> {
         }
     }
 
+    /// Closes the channel, so that sending fails from then on, without dropping the values
+    /// already in it.
+    ///
+    /// The values can still be received.
+    pub fn close(&mut self) {
+        for rx in &mut self.receivers {
+            rx.close();
+        }
+    }
+
+    /// Returns `true` if all the senders were dropped, or if the channel was closed with
+    /// [`Receiver::close`].
+    ///
+    /// The values sent before can still be received.
+    pub fn is_closed(&self) -> bool {
+        self.receivers.iter().all(|rx| rx.is_closed())
+    }
+
     /// Receives a value from the channel.
     ///
     /// This method will block (spin) until a value is available in any of the shards.
@@ -64,6 +82,35 @@ impl<T> Receiver<T> {
         }
     }
 
+    /// Returns an iterator receiving values without blocking, until all shards are empty.
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.try_recv())
+    }
+
+    /// Returns an iterator receiving values, blocking for each one until all the senders are
+    /// dropped and every shard is empty.
+    ///
+    /// It also ends once the channel is closed with [`Receiver::close`] and emptied.
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        crate::iter::blocking(self, Self::try_recv, Self::is_closed)
+    }
+
+    /// Returns an iterator receiving the values available in the shards when it is called.
+    ///
+    /// The shards are drained one after the other. Unlike [`Receiver::try_iter`], it ends even if
+    /// the senders keep sending. Values it didn't get to because it was dropped early stay in the
+    /// channel.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.receivers.iter_mut().flat_map(|rx| rx.drain())
+    }
+
+    /// Drops all the values available in the shards.
+    pub fn clear(&mut self) {
+        for rx in &mut self.receivers {
+            rx.clear();
+        }
+    }
+
     /// Returns a slice of the internal read buffer from one of the shards.
     ///
     /// If no elements are available in any shard, an empty slice is returned.
@@ -87,7 +134,8 @@ impl<T> Receiver<T> {
         }
     }
 
-    /// Advances the read pointer of the last shard accessed by `read_buffer`.
+    /// Advances the read pointer of the last shard accessed by `read_buffer`, without dropping
+    /// the elements.
     ///
     /// # Safety
     ///
@@ -96,6 +144,18 @@ impl<T> Receiver<T> {
     pub unsafe fn advance(&mut self, len: usize) {
         unsafe { self.receivers[self.next_shard].advance(len) };
     }
+
+    /// Advances the read pointer of the last shard accessed by `read_buffer`, dropping the
+    /// elements.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `len` is less than or equal to the length of the slice
+    /// returned by the last call to `read_buffer`, and that none of these elements were moved
+    /// out of it.
+    pub unsafe fn discard(&mut self, len: usize) {
+        unsafe { self.receivers[self.next_shard].discard(len) };
+    }
 }
 
-unsafe impl<T> Send for Receiver<T> {}
+unsafe impl<T: Send, A: Allocator + Clone + Send> Send for Receiver<T, A> {}
@@ -1,47 +1,56 @@
-use core::{mem::MaybeUninit, num::NonZeroUsize, ptr::NonNull};
+use core::{mem::MaybeUninit, num::NonZeroUsize};
 
 use crate::{
-    Box,
+    Allocator, Global,
     spsc::{self, shards::ShardsPtr},
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::atomic::Ordering,
 };
 
 /// The sending half of a sharded MPSC channel.
 ///
 /// Each sender is bound to a specific shard. Cloning a sender will attempt to bind the new
-/// instance to a different, unused shard.
-pub struct Sender<T> {
-    inner: spsc::Sender<T>,
-    shards: ShardsPtr<T>,
-    num_senders: NonNull<AtomicUsize>,
+/// instance to a different, unused shard. Shards aren't reused, so the shard of a dropped
+/// sender can't be taken again.
+pub struct Sender<T, A: Allocator + Clone = Global> {
+    inner: spsc::Sender<T, A>,
+    shards: ShardsPtr<T, A>,
     max_shards: usize,
 }
 
-impl<T> Sender<T> {
-    pub(crate) fn new(shards: ShardsPtr<T>, max_shards: NonZeroUsize) -> Self {
-        let num_senders_ptr = Box::into_raw(Box::new(AtomicUsize::new(0)));
-        unsafe {
-            let num_senders = NonNull::new_unchecked(num_senders_ptr);
-            Self::init(shards, max_shards.get(), num_senders).unwrap_unchecked()
-        }
+impl<T, A: Allocator + Clone> Sender<T, A> {
+    pub(crate) fn new(shards: ShardsPtr<T, A>, max_shards: NonZeroUsize) -> Self {
+        shards.senders().fetch_add(1, Ordering::AcqRel);
+        unsafe { Self::init(shards, max_shards.get()).unwrap_unchecked() }
     }
 
     /// Attempts to clone the sender.
     ///
     /// Returns `Some(Sender)` if there is an available shard to bind to, otherwise returns `None`.
+    #[allow(clippy::should_implement_trait)]
     pub fn clone(&self) -> Option<Self> {
-        unsafe { Self::init(self.shards.clone(), self.max_shards, self.num_senders) }
+        self.shards.senders().fetch_add(1, Ordering::AcqRel);
+        Self::init(self.shards.clone(), self.max_shards)
+    }
+
+    /// Creates a [`WeakSender`] for this channel.
+    ///
+    /// The weak handle isn't bound to a shard and doesn't keep the channel open: once all the
+    /// [`Sender`]s are dropped, the channel is disconnected even if weak handles remain.
+    pub fn downgrade(&self) -> WeakSender<T, A> {
+        WeakSender {
+            shards: self.shards.clone(),
+            max_shards: self.max_shards,
+        }
     }
 
-    pub(crate) unsafe fn init(
-        shards: ShardsPtr<T>,
-        max_shards: usize,
-        num_senders: NonNull<AtomicUsize>,
-    ) -> Option<Self> {
-        let num_senders_ref = unsafe { num_senders.as_ref() };
-        let next_shard = num_senders_ref.fetch_add(1, Ordering::AcqRel);
+    /// Binds a new sender to the next unused shard, the caller must already have counted it as
+    /// alive.
+    fn init(shards: ShardsPtr<T, A>, max_shards: usize) -> Option<Self> {
+        let claimed = shards.claimed();
+        let next_shard = claimed.fetch_add(1, Ordering::Relaxed);
         if next_shard >= max_shards {
-            num_senders_ref.fetch_sub(1, Ordering::AcqRel);
+            claimed.store(max_shards, Ordering::Relaxed);
+            shards.senders().fetch_sub(1, Ordering::AcqRel);
             return None;
         }
 
@@ -51,21 +60,33 @@ impl<T> Sender<T> {
         Some(Self {
             inner,
             shards,
-            num_senders,
             max_shards,
         })
     }
 
+    /// Returns `true` if the receiver closed the channel with [`Receiver::close`] or was
+    /// dropped.
+    ///
+    /// Sending fails from then on. This can be used to cancel the work producing the values
+    /// early.
+    ///
+    /// [`Receiver::close`]: super::Receiver::close
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
     /// Sends a value into the channel.
     ///
-    /// This method will block (spin) until there is space in the shard's queue.
+    /// This method will block (spin) until there is space in the shard's queue. If the channel is
+    /// closed, the value is dropped and this returns right away.
     pub fn send(&mut self, value: T) {
         self.inner.send(value)
     }
 
     /// Attempts to send a value into the channel without blocking.
     ///
-    /// Returns `Ok(())` if the value was sent, or `Err(value)` if the shard's queue is full.
+    /// Returns `Ok(())` if the value was sent, or `Err(value)` if the shard's queue is full or the
+    /// channel is closed.
     pub fn try_send(&mut self, value: T) -> Result<(), T> {
         self.inner.try_send(value)
     }
@@ -84,14 +105,58 @@ impl<T> Sender<T> {
     }
 }
 
-impl<T> Drop for Sender<T> {
+impl<T, A: Allocator + Clone> Drop for Sender<T, A> {
     fn drop(&mut self) {
-        let num_senders_ref = unsafe { self.num_senders.as_ref() };
-        if num_senders_ref.fetch_sub(1, Ordering::AcqRel) == 1 {
-            // creating a box so that heap allocation is also freed
-            _ = unsafe { Box::from_raw(self.num_senders.as_ptr()) };
+        self.shards.senders().fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+unsafe impl<T: Send, A: Allocator + Clone + Send> Send for Sender<T, A> {}
+
+/// A handle to the channel that doesn't keep it open, created with [`Sender::downgrade`].
+///
+/// Useful when the consumer needs a way to send to its own channel without preventing it from
+/// being disconnected, as with an actor holding a handle to its mailbox.
+pub struct WeakSender<T, A: Allocator + Clone = Global> {
+    shards: ShardsPtr<T, A>,
+    max_shards: usize,
+}
+
+impl<T, A: Allocator + Clone> WeakSender<T, A> {
+    /// Attempts to turn this into a [`Sender`] bound to an unused shard.
+    ///
+    /// Returns `None` if all the senders were already dropped, or if there is no shard left to
+    /// bind to. Shards aren't reused, so a shard freed by a dropped sender can't be taken again.
+    pub fn upgrade(&self) -> Option<Sender<T, A>> {
+        let senders = self.shards.senders();
+        let mut count = senders.load(Ordering::Relaxed);
+        loop {
+            // once the count reached 0 the channel is disconnected for good
+            if count == 0 {
+                return None;
+            }
+            match senders.compare_exchange_weak(
+                count,
+                count + 1,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(cur) => count = cur,
+            }
+        }
+
+        Sender::init(self.shards.clone(), self.max_shards)
+    }
+}
+
+impl<T, A: Allocator + Clone> Clone for WeakSender<T, A> {
+    fn clone(&self) -> Self {
+        Self {
+            shards: self.shards.clone(),
+            max_shards: self.max_shards,
         }
     }
 }
 
-unsafe impl<T> Send for Sender<T> {}
+unsafe impl<T: Send, A: Allocator + Clone + Send> Send for WeakSender<T, A> {}
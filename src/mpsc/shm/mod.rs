@@ -0,0 +1,463 @@
+//! Inter-process MPSC queue over shared memory.
+//!
+//! [`create`] places the queue header and its cells in a file-backed shared mapping, typically in
+//! `/dev/shm`, and [`open`] maps that same file in other processes after checking that its layout
+//! matches the one expected for `T`. Any number of processes can turn their [`Segment`] into a
+//! [`Sender`], and one of them into the [`Receiver`]. The queue uses the same cell epochs as the
+//! in-process [`mpsc`](crate::mpsc) queue.
+//!
+//! # Producer crashes
+//!
+//! A producer claims a cell by incrementing the shared tail, and publishes it by bumping the cell's
+//! epoch once the value is written. If the producer dies in between, the in-process algorithm would
+//! leave the consumer waiting on that cell forever. Here, once a claimed cell has stayed
+//! unpublished for longer than the [stall timeout](Receiver::set_stall_timeout), the receiver skips
+//! it and moves on:
+//!
+//! - a cell that was claimed but not written to yet is skipped. If its producer is only slow, it
+//!   notices the skip when it starts writing and claims a new cell instead.
+//! - a cell that is being written to records the pid of its producer, and is only reclaimed once
+//!   that process no longer exists.
+//!
+//! Skipped cells are counted by [`Receiver::skipped`].
+//!
+//! # Safety
+//!
+//! `T` is copied between processes as raw bytes, so it must not contain pointers, references or
+//! anything else that is only meaningful inside one process. All processes must also agree on the
+//! type: only its size and alignment can be checked when opening.
+//!
+//! There must be at most one [`Receiver`] for a segment across all processes. None of this can be
+//! checked, so [`open`], [`Segment::into_sender`] and [`Segment::into_receiver`] are `unsafe`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use core::num::NonZeroUsize;
+//! use gil::mpsc::shm;
+//!
+//! #[derive(Clone, Copy)]
+//! #[repr(C)]
+//! struct Order {
+//!     id: u64,
+//!     qty: u64,
+//! }
+//!
+//! // in the matching engine process
+//! let segment = shm::create::<Order>("/dev/shm/orders", NonZeroUsize::new(4096).unwrap())?;
+//! // SAFETY: `Order` is plain data, and this is the only receiver
+//! let mut rx = unsafe { segment.into_receiver() };
+//!
+//! // in each gateway process
+//! // SAFETY: the queue was created for `Order`
+//! let mut tx = unsafe { shm::open::<Order>("/dev/shm/orders")?.into_sender() };
+//! tx.send(Order { id: 1, qty: 10 });
+//!
+//! let order = rx.recv();
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use core::{
+    marker::PhantomData,
+    mem::{MaybeUninit, size_of},
+    num::NonZeroUsize,
+    ptr::NonNull,
+};
+use std::{io, path::Path, sync::Arc};
+
+use crate::{
+    alloc,
+    atomic::{AtomicU64, Ordering},
+    padded::Padded,
+    shm::{Mapping, Preamble, invalid},
+};
+
+pub use self::{receiver::Receiver, sender::Sender};
+
+mod receiver;
+mod sender;
+
+const MAGIC: u64 = u64::from_ne_bytes(*b"gil-mpsc");
+
+/// Set in the epoch of a cell while a producer is writing to it. The epoch then holds the pid of
+/// that producer in bits 32..63, and the low 32 bits of the index it is writing to.
+const WRITING: u64 = 1 << 63;
+
+/// # Invariants
+/// - tail should always point to the place where we can write next to.
+/// - head is only written to by the receiver, to resume from it when the queue is reopened.
+/// - everything but `head` and `tail` is immutable once the preamble is initialised.
+// avoid re-ordering fields, the layout is shared between processes
+#[repr(C)]
+struct Header {
+    preamble: Preamble,
+    head: Padded<AtomicU64>,
+    tail: Padded<AtomicU64>,
+}
+
+// avoid re-ordering fields, the layout is shared between processes
+#[repr(C)]
+struct Slot<T> {
+    epoch: AtomicU64,
+    data: MaybeUninit<T>,
+}
+
+pub(crate) struct SlotPtr<T> {
+    ptr: NonNull<Slot<T>>,
+}
+
+impl<T> SlotPtr<T> {
+    /// # Safety
+    /// The value must be initialised correctly at this `index`
+    #[inline(always)]
+    pub(crate) unsafe fn get(&self) -> T {
+        unsafe { _field!(Slot<T>, self.ptr, data, T).read() }
+    }
+
+    #[inline(always)]
+    pub(crate) fn set(&self, value: T) {
+        unsafe { _field!(Slot<T>, self.ptr, data, T).write(value) }
+    }
+
+    #[inline(always)]
+    pub(crate) fn epoch(&self) -> &AtomicU64 {
+        unsafe { _field!(Slot<T>, self.ptr, epoch, AtomicU64).as_ref() }
+    }
+}
+
+/// The epoch of a cell being written to at `index` by the process `pid`.
+#[inline(always)]
+pub(crate) fn writing(index: u64, pid: u32) -> u64 {
+    debug_assert!(pid < 1 << 31);
+    WRITING | (pid as u64) << 32 | (index & u32::MAX as u64)
+}
+
+/// The pid of the producer writing to a cell at `index`, if its `epoch` says so.
+#[inline(always)]
+pub(crate) fn writer(epoch: u64, index: u64) -> Option<u32> {
+    (epoch & WRITING != 0 && epoch as u32 == index as u32)
+        .then_some((epoch >> 32) as u32 & !(1 << 31))
+}
+
+/// Compares the round of the cell with `epoch` to the round of `index` on the same cell.
+#[inline(always)]
+pub(crate) fn round_cmp(epoch: u64, index: u64) -> core::cmp::Ordering {
+    if epoch & WRITING != 0 {
+        ((epoch as u32).wrapping_sub(index as u32) as i32).cmp(&0)
+    } else {
+        epoch.cmp(&index)
+    }
+}
+
+/// A shared-memory mapping holding an MPSC queue of `T`.
+///
+/// Created with [`create`] or [`open`], and turned into the end of the queue used by this process
+/// with [`Segment::into_sender`] or [`Segment::into_receiver`]. The mapping is unmapped when the
+/// last handle using it is dropped, but the backing file is left in place.
+pub struct Segment<T> {
+    map: Arc<Mapping>,
+    buffer: NonNull<Padded<Slot<T>>>,
+    pub(crate) capacity: u64,
+    pub(crate) mask: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Clone for Segment<T> {
+    fn clone(&self) -> Self {
+        Self {
+            map: Arc::clone(&self.map),
+            buffer: self.buffer,
+            capacity: self.capacity,
+            mask: self.mask,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Creates a new file at `path` holding an empty MPSC queue of `T`, and maps it.
+///
+/// The file must not exist yet. It is not removed when the queue is dropped, use
+/// [`std::fs::remove_file`] once no process needs it anymore.
+///
+/// See the [module-level documentation](self) for the requirements on `T`.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file, usually in `/dev/shm` so that it is only backed by memory.
+/// * `capacity` - The capacity of the queue, rounded up to a power of two of at least 2.
+pub fn create<T: Copy + 'static>(
+    path: impl AsRef<Path>,
+    capacity: NonZeroUsize,
+) -> io::Result<Segment<T>> {
+    // with a single cell, a published value couldn't be told apart from a free cell
+    let capacity = capacity.get().next_power_of_two().max(2);
+    let (layout, buffer_offset) = Segment::<T>::layout(capacity)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "queue capacity too large"))?;
+
+    let map = Mapping::create(path.as_ref(), layout.size())?;
+    // SAFETY: the mapping is at least `layout.size()` bytes long, and page aligned
+    let segment = unsafe { Segment::from_raw(map, buffer_offset, capacity) };
+
+    // SAFETY: the file was just created, so nobody else can have initialised it
+    unsafe {
+        segment.map.as_ptr().cast::<Header>().write(Header {
+            preamble: Preamble::new::<T>(MAGIC, size_of::<Header>(), buffer_offset, capacity),
+            head: Padded::new(AtomicU64::new(0)),
+            tail: Padded::new(AtomicU64::new(0)),
+        });
+    }
+    for idx in 0..segment.capacity {
+        segment.at(idx).epoch().store(idx, Ordering::Relaxed);
+    }
+    segment.header().preamble.set_initialised();
+
+    Ok(segment)
+}
+
+/// Maps the MPSC queue of `T` at `path`, which was created with [`create`].
+///
+/// # Safety
+///
+/// The queue must have been created for the same `T`, which must not contain pointers,
+/// references or anything else that is only meaningful inside one process. Only the size and
+/// alignment of `T` are checked.
+///
+/// # Errors
+///
+/// * [`io::ErrorKind::WouldBlock`] if the creator hasn't finished initialising the queue yet.
+/// * [`io::ErrorKind::InvalidData`] if the file doesn't hold a queue of this version of the crate,
+///   or the queue was created for a type with a different size or alignment.
+/// * Any error from opening or mapping the file.
+pub unsafe fn open<T: Copy + 'static>(path: impl AsRef<Path>) -> io::Result<Segment<T>> {
+    let map = Mapping::open(path.as_ref(), size_of::<Header>())?;
+    // SAFETY: the mapping is at least as long as the header, and page aligned
+    let header = unsafe { map.as_ptr().cast::<Header>().as_ref() };
+
+    let (capacity, buffer_offset) = header.preamble.check::<T>(MAGIC, size_of::<Header>())?;
+    if capacity < 2 {
+        return Err(invalid("invalid queue capacity"));
+    }
+
+    let (layout, expected_offset) =
+        Segment::<T>::layout(capacity).ok_or_else(|| invalid("invalid queue capacity"))?;
+    if buffer_offset != expected_offset || layout.size() > map.len() {
+        return Err(invalid("queue buffer layout mismatch"));
+    }
+
+    // SAFETY: the mapping holds a valid header and `capacity` cells
+    Ok(unsafe { Segment::from_raw(map, buffer_offset, capacity) })
+}
+
+impl<T> Segment<T> {
+    /// Turns the mapping into a producer end of the queue.
+    ///
+    /// The segment can be cloned first to get more than one sender in the same process.
+    ///
+    /// # Safety
+    ///
+    /// `T` must not contain pointers, references or anything else that is only meaningful inside
+    /// one process.
+    pub unsafe fn into_sender(self) -> Sender<T> {
+        Sender::new(self)
+    }
+
+    /// Turns the mapping into the consumer end of the queue.
+    ///
+    /// # Safety
+    ///
+    /// There must be no other [`Receiver`] for this queue in any process, and `T` must not contain
+    /// pointers, references or anything else that is only meaningful inside one process.
+    pub unsafe fn into_receiver(self) -> Receiver<T> {
+        Receiver::new(self)
+    }
+
+    /// # Safety
+    /// `map` must be long enough to hold the header and `capacity` cells at `buffer_offset`.
+    unsafe fn from_raw(map: Mapping, buffer_offset: usize, capacity: usize) -> Self {
+        Self {
+            buffer: unsafe { map.as_ptr().byte_add(buffer_offset).cast() },
+            map: Arc::new(map),
+            capacity: capacity as u64,
+            mask: capacity as u64 - 1,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns `None` if a buffer of `capacity` elements doesn't fit in the address space.
+    fn layout(capacity: usize) -> Option<(alloc::Layout, usize)> {
+        let header_layout = alloc::Layout::new::<Header>();
+        let buffer_layout = alloc::Layout::array::<Padded<Slot<T>>>(capacity).ok()?;
+        let (layout, offset) = header_layout.extend(buffer_layout).ok()?;
+        Some((layout.pad_to_align(), offset))
+    }
+
+    #[inline(always)]
+    fn header(&self) -> &Header {
+        unsafe { self.map.as_ptr().cast::<Header>().as_ref() }
+    }
+
+    #[inline(always)]
+    pub(crate) fn head(&self) -> &AtomicU64 {
+        &self.header().head.value
+    }
+
+    #[inline(always)]
+    pub(crate) fn tail(&self) -> &AtomicU64 {
+        &self.header().tail.value
+    }
+
+    #[inline(always)]
+    pub(crate) fn at(&self, index: u64) -> SlotPtr<T> {
+        let ptr = unsafe { self.buffer.add((index & self.mask) as usize) };
+        SlotPtr { ptr: ptr.cast() }
+    }
+}
+
+unsafe impl<T: Send> Send for Segment<T> {}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+
+    use super::*;
+    use crate::{shm::temp_path, thread};
+
+    #[test]
+    fn basic() {
+        const THREADS: u32 = 8;
+        const ITER: u32 = 1000;
+
+        let path = temp_path();
+        let segment = create::<(u32, u32)>(&path, NonZeroUsize::new(16).unwrap()).unwrap();
+        let mut rx = unsafe { segment.into_receiver() };
+
+        thread::scope(|scope| {
+            for thread_id in 0..THREADS {
+                let mut tx = unsafe { open::<(u32, u32)>(&path).unwrap().into_sender() };
+                scope.spawn(move || {
+                    for i in 0..ITER {
+                        tx.send((thread_id, i));
+                    }
+                });
+            }
+
+            let mut next = [0; THREADS as usize];
+            for _ in 0..THREADS * ITER {
+                let (thread_id, i) = rx.recv();
+                assert_eq!(next[thread_id as usize], i);
+                next[thread_id as usize] += 1;
+            }
+        });
+
+        assert_eq!(rx.skipped(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_valid_try_sends() {
+        let path = temp_path();
+        let segment = create::<usize>(&path, NonZeroUsize::new(4).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut tx = unsafe { segment.clone().into_sender() };
+        let mut rx = unsafe { segment.into_receiver() };
+
+        for _ in 0..4 {
+            assert!(rx.try_recv().is_none());
+        }
+        for i in 0..4 {
+            tx.try_send(i).unwrap();
+        }
+        assert!(tx.try_send(5).is_err());
+
+        for i in 0..4 {
+            assert_eq!(rx.try_recv(), Some(i));
+        }
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_skip_claimed_cell() {
+        let path = temp_path();
+        let segment = create::<u32>(&path, NonZeroUsize::new(4).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut tx = unsafe { segment.clone().into_sender() };
+        let mut rx = unsafe { segment.clone().into_receiver() };
+        rx.set_stall_timeout(Duration::from_millis(10));
+
+        // a producer claims the first cell and dies before writing to it
+        segment.tail().fetch_add(1, Ordering::Relaxed);
+        tx.send(1);
+
+        assert!(rx.try_recv().is_none());
+        assert_eq!(rx.recv(), 1);
+        assert_eq!(rx.skipped(), 1);
+
+        // the cell was handed to the next round, so the late producer can't start writing to it
+        assert_eq!(segment.at(0).epoch().load(Ordering::Relaxed), 4);
+        tx.send(2);
+        assert_eq!(rx.recv(), 2);
+    }
+
+    #[test]
+    fn test_reclaim_dead_writer() {
+        let path = temp_path();
+        let segment = create::<u32>(&path, NonZeroUsize::new(4).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut tx = unsafe { segment.clone().into_sender() };
+        let mut rx = unsafe { segment.clone().into_receiver() };
+        rx.set_stall_timeout(Duration::from_millis(10));
+
+        // a live producer is writing to the first cell, it is never reclaimed
+        let index = segment.tail().fetch_add(1, Ordering::Relaxed);
+        let live = writing(index, std::process::id());
+        segment.at(index).epoch().store(live, Ordering::Relaxed);
+        tx.send(1);
+
+        for _ in 0..3 {
+            thread::sleep(Duration::from_millis(10));
+            assert!(rx.try_recv().is_none());
+        }
+
+        // the producer writing to it died
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        segment
+            .at(index)
+            .epoch()
+            .store(writing(index, pid), Ordering::Relaxed);
+
+        assert_eq!(rx.recv(), 1);
+        assert_eq!(rx.skipped(), 1);
+    }
+
+    #[test]
+    fn test_layout_mismatch() {
+        let path = temp_path();
+        let _segment = create::<u32>(&path, NonZeroUsize::new(8).unwrap()).unwrap();
+
+        let err = unsafe { open::<u64>(&path) }.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = unsafe { crate::spsc::shm::open::<u32>(&path) }
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_capacity() {
+        let path = temp_path();
+        let segment = create::<u32>(&path, NonZeroUsize::new(8).unwrap()).unwrap();
+
+        // a capacity whose buffer doesn't fit in the address space
+        let capacity = segment.map.as_ptr().cast::<u64>();
+        unsafe { capacity.add(6).write(1 << (usize::BITS - 2)) };
+
+        let err = unsafe { open::<u32>(&path) }.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
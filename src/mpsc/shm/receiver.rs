@@ -0,0 +1,184 @@
+use std::{io, time::Instant};
+
+use core::time::Duration;
+
+use crate::{
+    atomic::Ordering,
+    mpsc::shm::{Segment, writer},
+};
+
+/// How long a claimed cell may stay unpublished before the receiver skips it, unless
+/// [`Receiver::set_stall_timeout`] is used.
+const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The consumer end of a shared-memory MPSC queue.
+///
+/// This struct is `Send` but not `Sync` or `Clone`. It can be moved to another thread, but cannot be shared
+/// across threads.
+pub struct Receiver<T> {
+    segment: Segment<T>,
+    local_head: u64,
+    stall_timeout: Duration,
+    /// Epoch of the cell at `local_head` when we first saw it stalled, and when that was.
+    stalled: Option<(u64, Instant)>,
+    skipped: u64,
+}
+
+impl<T> Receiver<T> {
+    pub(crate) fn new(segment: Segment<T>) -> Self {
+        // the queue may have been used by another process already
+        let local_head = segment.head().load(Ordering::Relaxed);
+        Self {
+            segment,
+            local_head,
+            stall_timeout: DEFAULT_STALL_TIMEOUT,
+            stalled: None,
+            skipped: 0,
+        }
+    }
+
+    /// Sets how long a cell claimed by a producer may stay unpublished before it is skipped.
+    ///
+    /// Cells a producer is still writing to are only skipped once that producer's process is gone,
+    /// see the [module-level documentation](super) for details. Defaults to 100ms.
+    pub fn set_stall_timeout(&mut self, timeout: Duration) {
+        self.stall_timeout = timeout;
+    }
+
+    /// Returns the number of cells skipped by this receiver because their producer stalled or died.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Receives a value from the queue, blocking if necessary.
+    ///
+    /// This method uses a spin loop to wait for available data in the queue.
+    /// For a non-blocking alternative, use [`Receiver::try_recv`].
+    pub fn recv(&mut self) -> T {
+        let mut backoff = crate::Backoff::with_spin_count(16);
+        loop {
+            if let Some(ret) = self.try_recv() {
+                return ret;
+            }
+            backoff.backoff();
+        }
+    }
+
+    /// Attempts to receive a value from the queue without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(value)` if a value is available.
+    /// * `None` if the queue is empty, or the next value hasn't been published by its producer yet.
+    pub fn try_recv(&mut self) -> Option<T> {
+        loop {
+            let cell = self.segment.at(self.local_head);
+            let epoch = cell.epoch().load(Ordering::Acquire);
+            let next_round = self.local_head.wrapping_add(self.segment.capacity);
+
+            if epoch == self.local_head.wrapping_add(1) {
+                let ret = unsafe { cell.get() };
+                cell.epoch().store(next_round, Ordering::Release);
+                self.advance();
+
+                return Some(ret);
+            }
+
+            if !self.is_stalled(epoch) {
+                return None;
+            }
+
+            // the producer is gone or too slow, hand the cell to the next round. A slow producer
+            // fails to swap the epoch once it gets to it and claims another cell.
+            if cell
+                .epoch()
+                .compare_exchange(epoch, next_round, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                self.skipped += 1;
+                self.advance();
+            }
+        }
+    }
+
+    /// Returns an iterator receiving values without blocking, until the queue is empty, or the next
+    /// value hasn't been published yet.
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.try_recv())
+    }
+
+    /// Returns an iterator receiving values, blocking for each one.
+    ///
+    /// Senders in other processes can't be tracked, so the iterator never ends. Use
+    /// [`Receiver::try_iter`] to stop once the queue is empty instead.
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::repeat_with(|| self.recv())
+    }
+
+    /// Returns an iterator receiving the values available in the queue when it is called.
+    ///
+    /// Unlike [`Receiver::try_iter`], it ends even if the senders keep sending. Values it didn't
+    /// get to because it was dropped early stay in the queue.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        let len = self
+            .segment
+            .tail()
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.local_head);
+        self.try_iter().take(len as usize)
+    }
+
+    /// Drops all the values available in the queue.
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+
+    #[inline(always)]
+    fn advance(&mut self) {
+        self.local_head = self.local_head.wrapping_add(1);
+        self.segment
+            .head()
+            .store(self.local_head, Ordering::Release);
+        self.stalled = None;
+    }
+
+    /// Whether the cell at the head, with `epoch`, was claimed by a producer which has been
+    /// stalled or dead for longer than the timeout.
+    #[cold]
+    fn is_stalled(&mut self, epoch: u64) -> bool {
+        let pid = writer(epoch, self.local_head);
+        let claimed = epoch == self.local_head
+            && self.segment.tail().load(Ordering::Acquire) > self.local_head;
+        if pid.is_none() && !claimed {
+            self.stalled = None;
+            return false;
+        }
+
+        let now = Instant::now();
+        match self.stalled {
+            Some((stalled, since)) if stalled == epoch => {
+                if now.duration_since(since) < self.stall_timeout {
+                    return false;
+                }
+                if pid.is_some_and(is_alive) {
+                    // still writing, check on it again after another timeout
+                    self.stalled = Some((epoch, now));
+                    return false;
+                }
+                true
+            }
+            _ => {
+                self.stalled = Some((epoch, now));
+                false
+            }
+        }
+    }
+}
+
+fn is_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks whether the process exists
+    let ret = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    ret == 0 || io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+unsafe impl<T: Send> Send for Receiver<T> {}
@@ -0,0 +1,125 @@
+use core::cmp::Ordering as Cmp;
+
+use crate::{
+    atomic::Ordering,
+    mpsc::shm::{Segment, SlotPtr, round_cmp, writing},
+};
+
+/// A producer end of a shared-memory MPSC queue.
+///
+/// This struct is `Clone` and `Send`. It can be shared across threads by cloning it.
+#[derive(Clone)]
+pub struct Sender<T> {
+    segment: Segment<T>,
+    pid: u32,
+}
+
+impl<T> Sender<T> {
+    pub(crate) fn new(segment: Segment<T>) -> Self {
+        Self {
+            segment,
+            pid: std::process::id(),
+        }
+    }
+}
+
+impl<T: Copy> Sender<T> {
+    /// Sends a value into the queue, blocking if necessary.
+    ///
+    /// This method uses a spin loop to wait for available space in the queue.
+    /// For a non-blocking alternative, use [`Sender::try_send`].
+    pub fn send(&mut self, value: T) {
+        let mut backoff = crate::Backoff::with_spin_count(128);
+        loop {
+            // fetch_add means we are the only producer who can access the cell at this idx
+            let tail = self.segment.tail().fetch_add(1, Ordering::Relaxed);
+
+            let cell = self.segment.at(tail);
+            loop {
+                match round_cmp(cell.epoch().load(Ordering::Acquire), tail) {
+                    // consumer hasn't read the previous value yet
+                    Cmp::Less => backoff.backoff(),
+                    Cmp::Equal => {
+                        if self.write(&cell, tail, value) {
+                            return;
+                        }
+                        break;
+                    }
+                    // consumer gave up on this cell, claim another one
+                    Cmp::Greater => break,
+                }
+            }
+        }
+    }
+
+    /// Attempts to send a value into the queue without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the value was successfully sent.
+    /// * `Err(value)` if the queue is full, returning the original value.
+    pub fn try_send(&mut self, value: T) -> Result<(), T> {
+        let mut backoff = crate::Backoff::with_spin_count(16);
+        let mut tail = self.segment.tail().load(Ordering::Relaxed);
+
+        loop {
+            let cell = self.segment.at(tail);
+            let epoch = cell.epoch().load(Ordering::Acquire);
+
+            match round_cmp(epoch, tail) {
+                // consumer hasn't read the previous value
+                Cmp::Less => return Err(value),
+
+                // consumer has read the value, cell is free
+                Cmp::Equal if epoch == tail => {
+                    let next = tail.wrapping_add(1);
+                    match self.segment.tail().compare_exchange_weak(
+                        tail,
+                        next,
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(_) => {
+                            if self.write(&cell, tail, value) {
+                                return Ok(());
+                            }
+                            tail = next;
+                        }
+                        Err(cur_tail) => tail = cur_tail,
+                    }
+                }
+
+                // some other producer has claimed this cell before us
+                _ => tail = self.segment.tail().load(Ordering::Relaxed),
+            }
+
+            backoff.backoff();
+        }
+    }
+
+    /// Writes `value` to the cell claimed at `index`, returns `false` if the receiver skipped the
+    /// cell before we could start or finish writing to it.
+    #[inline(always)]
+    fn write(&self, cell: &SlotPtr<T>, index: u64, value: T) -> bool {
+        let writing = writing(index, self.pid);
+        if cell
+            .epoch()
+            .compare_exchange(index, writing, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+
+        cell.set(value);
+        cell.epoch()
+            .compare_exchange(
+                writing,
+                index.wrapping_add(1),
+                Ordering::Release,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+    }
+}
+
+unsafe impl<T: Send> Send for Sender<T> {}
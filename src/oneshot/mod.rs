@@ -0,0 +1,292 @@
+//! Oneshot channel for handing over a single value.
+//!
+//! # Performance
+//!
+//! The channel is a single small allocation holding the value and one state word, which makes it
+//! much cheaper to create than a one-slot [`spsc`](crate::spsc) queue with its padded head and
+//! tail indices. Sending never blocks, and the value is moved exactly once in each direction.
+//!
+//! # When to use
+//!
+//! Use this channel for request/response patterns, where every request carries a channel for its
+//! single response. Both ends notice when the other one is dropped: the receiver gets a
+//! [`RecvError`] if the sender is dropped without sending, and the sender can check
+//! [`Sender::is_closed`] to stop working on a response nobody is waiting for anymore.
+
+use core::fmt;
+
+pub use self::{receiver::Receiver, sender::Sender};
+#[cfg(feature = "async")]
+pub use self::{receiver::RecvFuture, sender::ClosedFuture};
+
+mod receiver;
+mod sender;
+mod slot;
+
+/// Creates a new oneshot channel.
+///
+/// See the [module-level documentation](self) for more details on performance and usage.
+///
+/// # Returns
+///
+/// A tuple containing the [`Sender`] and [`Receiver`] handles.
+///
+/// # Examples
+///
+/// ```
+/// use gil::oneshot::channel;
+///
+/// let (tx, rx) = channel::<usize>();
+///
+/// std::thread::spawn(move || {
+///     tx.send(42).unwrap();
+/// });
+///
+/// assert_eq!(rx.recv(), Ok(42));
+/// ```
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let slot = slot::SlotPtr::new();
+    (Sender::new(slot.clone()), Receiver::new(slot))
+}
+
+/// Error returned by [`Receiver::recv`] when the sender was dropped without sending a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sender dropped without sending a value")
+    }
+}
+
+impl core::error::Error for RecvError {}
+
+/// Error returned by [`Receiver::try_recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// The value wasn't sent yet.
+    Empty,
+    /// The sender was dropped without sending a value, or the value was already received.
+    Disconnected,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("value not sent yet"),
+            Self::Disconnected => f.write_str("sender dropped without sending a value"),
+        }
+    }
+}
+
+impl core::error::Error for TryRecvError {}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod test {
+    use super::*;
+
+    use crate::thread;
+
+    #[test]
+    fn basic() {
+        for i in 0..1000 {
+            let (tx, rx) = channel::<usize>();
+
+            thread::spawn(move || {
+                tx.send(i).unwrap();
+            });
+
+            assert_eq!(rx.recv(), Ok(i));
+        }
+    }
+
+    #[test]
+    fn test_try_recv() {
+        let (tx, mut rx) = channel::<usize>();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        tx.send(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_sender_dropped() {
+        let (tx, mut rx) = channel::<usize>();
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(rx.recv(), Err(RecvError));
+
+        let (tx, rx) = channel::<usize>();
+        thread::spawn(move || drop(tx));
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn test_receiver_dropped() {
+        let (tx, rx) = channel::<usize>();
+        assert!(!tx.is_closed());
+        drop(rx);
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(1), Err(1));
+    }
+
+    #[test]
+    fn test_drop_unreceived_value() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct DropCounter(Arc<AtomicUsize>);
+
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let dropped_count = Arc::new(AtomicUsize::new(0));
+
+        {
+            let (tx, rx) = channel::<DropCounter>();
+            assert!(tx.send(DropCounter(dropped_count.clone())).is_ok());
+            drop(rx);
+        }
+        assert_eq!(dropped_count.load(Ordering::SeqCst), 1);
+
+        {
+            let (tx, rx) = channel::<DropCounter>();
+            drop(rx);
+            let ret = tx.send(DropCounter(dropped_count.clone()));
+            assert!(ret.is_err());
+        }
+        assert_eq!(dropped_count.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_recv() {
+        futures::executor::block_on(async {
+            for i in 0..1000 {
+                let (tx, rx) = channel::<usize>();
+
+                thread::spawn(move || {
+                    tx.send(i).unwrap();
+                });
+
+                assert_eq!(rx.recv_async().await, Ok(i));
+            }
+
+            let (tx, rx) = channel::<usize>();
+            thread::spawn(move || drop(tx));
+            assert_eq!(rx.recv_async().await, Err(RecvError));
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_poll_recv() {
+        use core::{
+            future::Future,
+            pin::Pin,
+            task::{Context, Poll},
+        };
+
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        let (tx, mut rx) = channel::<usize>();
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+        tx.send(1).unwrap();
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Ok(1)));
+
+        let (tx, rx) = channel::<usize>();
+        let mut fut: RecvFuture<usize> = rx.recv_async();
+        assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+        drop(tx);
+        assert_eq!(
+            Pin::new(&mut fut).poll(&mut cx),
+            Poll::Ready(Err(RecvError))
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_closed() {
+        use core::{
+            future::Future,
+            pin::Pin,
+            task::{Context, Poll},
+        };
+
+        let (mut tx, rx) = channel::<usize>();
+        let handle = thread::spawn(move || drop(rx));
+        futures::executor::block_on(tx.closed());
+        handle.join().unwrap();
+
+        // waiting for the close doesn't get in the way of sending
+        let (mut tx, rx) = channel::<usize>();
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        let mut closed = tx.closed();
+        assert_eq!(Pin::new(&mut closed).poll(&mut cx), Poll::Pending);
+        drop(closed);
+        tx.send(0).unwrap();
+        assert_eq!(rx.recv(), Ok(0));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_cancel() {
+        use core::{
+            future::Future,
+            pin::Pin,
+            task::{Context, Poll},
+        };
+
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        let (tx, rx) = channel::<usize>();
+        let mut fut = rx.recv_async();
+        assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+        let mut rx = fut.into_inner();
+        assert!(!tx.is_closed());
+        tx.send(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+
+        let (tx, rx) = channel::<usize>();
+        let mut fut = rx.recv_async();
+        assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+        drop(fut);
+        assert!(tx.is_closed());
+    }
+}
+
+#[cfg(all(test, feature = "loom"))]
+mod loom_test {
+    use super::*;
+    use crate::thread;
+
+    #[test]
+    fn basic_loom() {
+        loom::model(|| {
+            let (tx, rx) = channel::<usize>();
+
+            thread::spawn(move || {
+                let _ = tx.send(1);
+            });
+
+            assert_eq!(rx.recv(), Ok(1));
+        })
+    }
+
+    #[test]
+    fn receiver_dropped_loom() {
+        loom::model(|| {
+            let (tx, rx) = channel::<usize>();
+
+            let handle = thread::spawn(move || tx.send(1));
+            drop(rx);
+
+            // either the value was sent and dropped by the receiver, or it is returned here
+            let _ = handle.join().unwrap();
+        })
+    }
+}
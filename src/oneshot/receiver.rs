@@ -0,0 +1,160 @@
+#[cfg(feature = "async")]
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{
+    Backoff,
+    atomic::Ordering,
+    oneshot::{
+        RecvError, TryRecvError,
+        slot::{CLOSED, EMPTY, FULL, SlotPtr},
+    },
+};
+
+/// The consumer end of the oneshot channel.
+///
+/// This struct is `Send` but not `Sync` or `Clone`. Dropping it closes the channel, which the
+/// sender observes through [`Sender::is_closed`](super::Sender::is_closed) and a failing
+/// [`Sender::send`](super::Sender::send).
+pub struct Receiver<T> {
+    ptr: SlotPtr<T>,
+}
+
+impl<T> Receiver<T> {
+    pub(crate) fn new(slot_ptr: SlotPtr<T>) -> Self {
+        Self { ptr: slot_ptr }
+    }
+
+    /// Attempts to receive the value without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(value)` if the value was sent.
+    /// * `Err(TryRecvError::Empty)` if the value wasn't sent yet.
+    /// * `Err(TryRecvError::Disconnected)` if the sender was dropped without sending, or the
+    ///   value was already received.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        match self.ptr.state().load(Ordering::Acquire) {
+            EMPTY => Err(TryRecvError::Empty),
+            FULL => {
+                // SAFETY: state is `FULL` so the value is initialised, and we are the only ones
+                //         allowed to take it
+                let ret = unsafe { self.ptr.read() };
+                // the sender is done with the slot, so nobody else can observe this
+                self.ptr.state().store(CLOSED, Ordering::Relaxed);
+                Ok(ret)
+            }
+            _ => Err(TryRecvError::Disconnected),
+        }
+    }
+
+    /// Receives the value, blocking if necessary.
+    ///
+    /// This method uses a spin loop to wait for the value.
+    /// For a non-blocking alternative, use [`Receiver::try_recv`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(value)` if the value was sent.
+    /// * `Err(RecvError)` if the sender was dropped without sending.
+    pub fn recv(mut self) -> Result<T, RecvError> {
+        let mut backoff = Backoff::with_spin_count(128);
+        loop {
+            match self.try_recv() {
+                Ok(ret) => return Ok(ret),
+                Err(TryRecvError::Empty) => backoff.backoff(),
+                Err(TryRecvError::Disconnected) => return Err(RecvError),
+            }
+        }
+    }
+
+    /// Receives the value asynchronously.
+    ///
+    /// The returned future yields the current task until the value is sent or the sender is
+    /// dropped.
+    ///
+    /// # Cancel safety
+    ///
+    /// The future owns the receiver, so dropping it closes the channel like dropping the receiver
+    /// does, and drops a value already sent. To keep the channel open, take the receiver back with
+    /// [`RecvFuture::into_inner`] instead.
+    #[cfg(feature = "async")]
+    pub fn recv_async(self) -> RecvFuture<T> {
+        RecvFuture { receiver: self }
+    }
+
+    /// Attempts to receive the value, registering the current task to be woken up if it wasn't
+    /// sent yet.
+    ///
+    /// # Returns
+    ///
+    /// * `Poll::Ready(Ok(value))` if the value was sent.
+    /// * `Poll::Ready(Err(RecvError))` if the sender was dropped without sending, or the value was
+    ///   already received.
+    /// * `Poll::Pending` if the value wasn't sent yet, the task is woken up once it is or the
+    ///   sender is dropped.
+    ///
+    /// After `Poll::Pending`, the waker stays registered until the next poll, until the value is
+    /// sent, or until the receiver is dropped.
+    #[cfg(feature = "async")]
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        match self.try_recv() {
+            Ok(ret) => return Poll::Ready(Ok(ret)),
+            Err(TryRecvError::Disconnected) => return Poll::Ready(Err(RecvError)),
+            Err(TryRecvError::Empty) => {}
+        }
+
+        self.ptr.register_receiver_waker(cx.waker());
+
+        // prevent lost wake
+        match self.try_recv() {
+            Ok(ret) => Poll::Ready(Ok(ret)),
+            Err(TryRecvError::Disconnected) => Poll::Ready(Err(RecvError)),
+            Err(TryRecvError::Empty) => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "async")]
+        self.ptr.unregister_receiver_waker();
+
+        match self.ptr.state().swap(CLOSED, Ordering::AcqRel) {
+            // SAFETY: the value was sent but never received, and the sender is done with it
+            FULL => unsafe { drop(self.ptr.read()) },
+            #[cfg(feature = "async")]
+            EMPTY => self.ptr.wake_sender(),
+            _ => {}
+        }
+    }
+}
+
+unsafe impl<T: Send> Send for Receiver<T> {}
+
+/// The future returned by [`Receiver::recv_async`].
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct RecvFuture<T> {
+    receiver: Receiver<T>,
+}
+
+#[cfg(feature = "async")]
+impl<T> RecvFuture<T> {
+    /// Cancels the receive, returning the receiver.
+    pub fn into_inner(self) -> Receiver<T> {
+        self.receiver
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> Future for RecvFuture<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.get_mut().receiver.poll_recv(cx)
+    }
+}
@@ -0,0 +1,136 @@
+#[cfg(feature = "async")]
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{
+    atomic::Ordering,
+    oneshot::slot::{CLOSED, EMPTY, FULL, SlotPtr},
+};
+
+/// The producer end of the oneshot channel.
+///
+/// This struct is `Send` but not `Sync` or `Clone`. Dropping it without sending a value closes the
+/// channel, which the receiver observes as a [`RecvError`](super::RecvError).
+pub struct Sender<T> {
+    ptr: SlotPtr<T>,
+}
+
+impl<T> Sender<T> {
+    pub(crate) fn new(slot_ptr: SlotPtr<T>) -> Self {
+        Self { ptr: slot_ptr }
+    }
+
+    /// Sends the value to the receiver, consuming the sender.
+    ///
+    /// This never blocks.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the value was handed over to the receiver.
+    /// * `Err(value)` if the receiver was dropped, returning the original value.
+    pub fn send(self, value: T) -> Result<(), T> {
+        // SAFETY: we are the sender and the state can only be `EMPTY` or `CLOSED` before we
+        //         change it, and the receiver never touches the value in either
+        unsafe { self.ptr.write(value) };
+
+        match self
+            .ptr
+            .state()
+            .compare_exchange(EMPTY, FULL, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => {
+                #[cfg(feature = "async")]
+                self.ptr.wake_receiver();
+                Ok(())
+            }
+            // SAFETY: the receiver is gone, so the value we just wrote is still ours
+            Err(_) => Err(unsafe { self.ptr.read() }),
+        }
+    }
+
+    /// Returns `true` if the receiver was dropped, in which case sending would fail.
+    ///
+    /// This can be used to cancel the work producing the value early.
+    pub fn is_closed(&self) -> bool {
+        self.ptr.state().load(Ordering::Acquire) == CLOSED
+    }
+
+    /// Waits until the receiver is dropped.
+    ///
+    /// # Cancel safety
+    ///
+    /// The future is cancel safe. The waker it registered is dropped with it.
+    #[cfg(feature = "async")]
+    pub fn closed(&mut self) -> ClosedFuture<'_, T> {
+        ClosedFuture {
+            sender: self,
+            waiting: false,
+        }
+    }
+
+    /// Checks whether the receiver was dropped, registering the current task to be woken up once
+    /// it is otherwise.
+    #[cfg(feature = "async")]
+    pub fn poll_closed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.is_closed() {
+            return Poll::Ready(());
+        }
+
+        // pairs with the wake of the receiver being dropped
+        self.ptr.register_sender_waker(cx.waker());
+        if self.is_closed() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        // fails if we already sent a value, or if the receiver is gone
+        if self
+            .ptr
+            .state()
+            .compare_exchange(EMPTY, CLOSED, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+        {
+            #[cfg(feature = "async")]
+            self.ptr.wake_receiver();
+        }
+    }
+}
+
+unsafe impl<T: Send> Send for Sender<T> {}
+
+/// The future returned by [`Sender::closed`].
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct ClosedFuture<'a, T> {
+    sender: &'a mut Sender<T>,
+    /// Whether the last poll registered the waker.
+    waiting: bool,
+}
+
+#[cfg(feature = "async")]
+impl<T> Future for ClosedFuture<'_, T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        let ret = this.sender.poll_closed(cx);
+        this.waiting = ret.is_pending();
+        ret
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> Drop for ClosedFuture<'_, T> {
+    fn drop(&mut self) {
+        if self.waiting {
+            self.sender.ptr.unregister_sender_waker();
+        }
+    }
+}
@@ -0,0 +1,162 @@
+#[cfg(feature = "async")]
+use core::task::Waker;
+use core::{mem::MaybeUninit, ptr::NonNull};
+
+#[cfg(feature = "async")]
+use futures::task::AtomicWaker;
+
+use crate::{
+    Box,
+    atomic::{AtomicUsize, Ordering},
+};
+
+/// No value was sent yet, and both ends are alive.
+pub(crate) const EMPTY: usize = 0;
+/// The value was sent, and the receiver hasn't taken it yet.
+pub(crate) const FULL: usize = 1;
+/// One of the ends is gone, or the value was already taken. The slot never holds a value in this
+/// state.
+pub(crate) const CLOSED: usize = 2;
+
+/// # Invariants
+/// - `value` is initialised iff `state` is `FULL`.
+/// - only the sender moves `state` out of `EMPTY`, and only the receiver moves it out of `FULL`.
+// avoid re-ordering fields
+#[repr(C)]
+struct Slot<T> {
+    state: AtomicUsize,
+    rc: AtomicUsize,
+    #[cfg(feature = "async")]
+    receiver_waker: AtomicWaker,
+    #[cfg(feature = "async")]
+    sender_waker: AtomicWaker,
+    value: MaybeUninit<T>,
+}
+
+pub(crate) struct SlotPtr<T> {
+    ptr: NonNull<Slot<T>>,
+}
+
+impl<T> Clone for SlotPtr<T> {
+    fn clone(&self) -> Self {
+        self.rc().fetch_add(1, Ordering::AcqRel);
+        Self { ptr: self.ptr }
+    }
+}
+
+impl<T> SlotPtr<T> {
+    pub(crate) fn new() -> Self {
+        let ptr = Box::into_raw(Box::new(Slot {
+            state: AtomicUsize::new(EMPTY),
+            rc: AtomicUsize::new(1),
+            #[cfg(feature = "async")]
+            receiver_waker: AtomicWaker::new(),
+            #[cfg(feature = "async")]
+            sender_waker: AtomicWaker::new(),
+            value: MaybeUninit::uninit(),
+        }));
+
+        // SAFETY: `Box::into_raw` never returns null
+        Self {
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn state(&self) -> &AtomicUsize {
+        unsafe { _field!(Slot<T>, self.ptr, state, AtomicUsize).as_ref() }
+    }
+
+    #[inline(always)]
+    fn rc(&self) -> &AtomicUsize {
+        unsafe { _field!(Slot<T>, self.ptr, rc, AtomicUsize).as_ref() }
+    }
+
+    /// # Safety
+    /// Only the sender may call this, and only while `state` is `EMPTY`.
+    #[inline(always)]
+    pub(crate) unsafe fn write(&self, value: T) {
+        unsafe { _field!(Slot<T>, self.ptr, value, T).write(value) }
+    }
+
+    /// # Safety
+    /// The value must be initialised, and must not be read again afterwards.
+    #[inline(always)]
+    pub(crate) unsafe fn read(&self) -> T {
+        unsafe { _field!(Slot<T>, self.ptr, value, T).read() }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> SlotPtr<T> {
+    #[inline(always)]
+    pub(crate) fn register_receiver_waker(&self, waker: &Waker) {
+        unsafe {
+            _field!(Slot<T>, self.ptr, receiver_waker, AtomicWaker)
+                .as_ref()
+                .register(waker);
+        }
+    }
+
+    /// Forgets the waker of a receiver which stopped waiting, so that it isn't kept alive for
+    /// nothing.
+    #[inline(always)]
+    pub(crate) fn unregister_receiver_waker(&self) {
+        unsafe {
+            _field!(Slot<T>, self.ptr, receiver_waker, AtomicWaker)
+                .as_ref()
+                .take();
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn wake_receiver(&self) {
+        unsafe {
+            _field!(Slot<T>, self.ptr, receiver_waker, AtomicWaker)
+                .as_ref()
+                .wake();
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn register_sender_waker(&self, waker: &Waker) {
+        unsafe {
+            _field!(Slot<T>, self.ptr, sender_waker, AtomicWaker)
+                .as_ref()
+                .register(waker);
+        }
+    }
+
+    /// Forgets the waker of a sender which stopped waiting, so that it isn't kept alive for
+    /// nothing.
+    #[inline(always)]
+    pub(crate) fn unregister_sender_waker(&self) {
+        unsafe {
+            _field!(Slot<T>, self.ptr, sender_waker, AtomicWaker)
+                .as_ref()
+                .take();
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn wake_sender(&self) {
+        unsafe {
+            _field!(Slot<T>, self.ptr, sender_waker, AtomicWaker)
+                .as_ref()
+                .wake();
+        }
+    }
+}
+
+impl<T> Drop for SlotPtr<T> {
+    fn drop(&mut self) {
+        if self.rc().fetch_sub(1, Ordering::AcqRel) == 1 {
+            // the receiver drops or takes the value before letting go of the slot, so there is
+            // never a value left to drop here
+            debug_assert_ne!(self.state().load(Ordering::Relaxed), FULL);
+
+            // creating a box so that heap allocation is also freed
+            _ = unsafe { Box::from_raw(self.ptr.as_ptr()) };
+        }
+    }
+}
@@ -1,11 +1,127 @@
-#[repr(align(64))]
-#[cfg_attr(all(target_arch = "aarch64", target_os = "macos"), repr(align(128)))]
+/// Pads and aligns a value to the length of a cache line, to prevent false sharing.
+///
+/// The length defaults per target architecture, like `crossbeam_utils::CachePadded`:
+/// - 128 bytes on x86-64, aarch64 and powerpc64, whose prefetchers pull cache lines in pairs or
+///   whose lines are that long,
+/// - 256 bytes on s390x,
+/// - 32 bytes on arm, mips, sparc and hexagon,
+/// - 16 bytes on m68k,
+/// - 64 bytes everywhere else.
+///
+/// It is overridden by the `cache_line_32`, `cache_line_64`, `cache_line_128` and
+/// `cache_line_256` features. When several are enabled, the largest one wins.
+#[cfg_attr(feature = "cache_line_256", repr(align(256)))]
+#[cfg_attr(
+    all(feature = "cache_line_128", not(feature = "cache_line_256")),
+    repr(align(128))
+)]
+#[cfg_attr(
+    all(
+        feature = "cache_line_64",
+        not(any(feature = "cache_line_128", feature = "cache_line_256"))
+    ),
+    repr(align(64))
+)]
+#[cfg_attr(
+    all(
+        feature = "cache_line_32",
+        not(any(
+            feature = "cache_line_64",
+            feature = "cache_line_128",
+            feature = "cache_line_256"
+        ))
+    ),
+    repr(align(32))
+)]
+#[cfg_attr(
+    all(
+        not(any(
+            feature = "cache_line_32",
+            feature = "cache_line_64",
+            feature = "cache_line_128",
+            feature = "cache_line_256"
+        )),
+        any(
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            target_arch = "arm64ec",
+            target_arch = "powerpc64",
+        )
+    ),
+    repr(align(128))
+)]
+#[cfg_attr(
+    all(
+        not(any(
+            feature = "cache_line_32",
+            feature = "cache_line_64",
+            feature = "cache_line_128",
+            feature = "cache_line_256"
+        )),
+        target_arch = "s390x"
+    ),
+    repr(align(256))
+)]
+#[cfg_attr(
+    all(
+        not(any(
+            feature = "cache_line_32",
+            feature = "cache_line_64",
+            feature = "cache_line_128",
+            feature = "cache_line_256"
+        )),
+        any(
+            target_arch = "arm",
+            target_arch = "mips",
+            target_arch = "mips32r6",
+            target_arch = "mips64",
+            target_arch = "mips64r6",
+            target_arch = "sparc",
+            target_arch = "hexagon",
+        )
+    ),
+    repr(align(32))
+)]
+#[cfg_attr(
+    all(
+        not(any(
+            feature = "cache_line_32",
+            feature = "cache_line_64",
+            feature = "cache_line_128",
+            feature = "cache_line_256"
+        )),
+        target_arch = "m68k"
+    ),
+    repr(align(16))
+)]
+#[cfg_attr(
+    not(any(
+        feature = "cache_line_32",
+        feature = "cache_line_64",
+        feature = "cache_line_128",
+        feature = "cache_line_256",
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "arm64ec",
+        target_arch = "powerpc64",
+        target_arch = "s390x",
+        target_arch = "arm",
+        target_arch = "mips",
+        target_arch = "mips32r6",
+        target_arch = "mips64",
+        target_arch = "mips64r6",
+        target_arch = "sparc",
+        target_arch = "hexagon",
+        target_arch = "m68k",
+    )),
+    repr(align(64))
+)]
 pub(crate) struct Padded<T> {
     pub(crate) value: T,
 }
 
 impl<T> Padded<T> {
-    pub(crate) fn new(value: T) -> Self {
+    pub(crate) const fn new(value: T) -> Self {
         Self { value }
     }
 }
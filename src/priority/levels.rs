@@ -0,0 +1,62 @@
+use core::ptr::NonNull;
+
+use crate::{
+    Box,
+    atomic::{AtomicUsize, Ordering},
+    padded::Padded,
+};
+
+/// # Invariants
+/// - bit `n` of `ready` is set whenever level `n` *may* contain an item. A set bit on an empty
+///   level is fine, a cleared bit on a non-empty level is not, unless the item is still being
+///   published by a sender that is going to set the bit afterwards.
+// avoid re-ordering fields
+#[repr(C)]
+struct Levels {
+    ready: Padded<AtomicUsize>,
+    rc: AtomicUsize,
+}
+
+pub(crate) struct LevelsPtr {
+    ptr: NonNull<Levels>,
+}
+
+impl Clone for LevelsPtr {
+    fn clone(&self) -> Self {
+        self.rc().fetch_add(1, Ordering::AcqRel);
+        Self { ptr: self.ptr }
+    }
+}
+
+impl LevelsPtr {
+    pub(crate) fn new() -> Self {
+        let ptr = Box::into_raw(Box::new(Levels {
+            ready: Padded::new(AtomicUsize::new(0)),
+            rc: AtomicUsize::new(1),
+        }));
+
+        // SAFETY: `Box::into_raw` never returns null
+        Self {
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn ready(&self) -> &AtomicUsize {
+        unsafe { _field!(Levels, self.ptr, ready.value, AtomicUsize).as_ref() }
+    }
+
+    #[inline(always)]
+    fn rc(&self) -> &AtomicUsize {
+        unsafe { _field!(Levels, self.ptr, rc, AtomicUsize).as_ref() }
+    }
+}
+
+impl Drop for LevelsPtr {
+    fn drop(&mut self) {
+        if self.rc().fetch_sub(1, Ordering::AcqRel) == 1 {
+            // creating a box so that heap allocation is also freed
+            _ = unsafe { Box::from_raw(self.ptr.as_ptr()) };
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Multi-producer single-consumer (MPSC) priority queue.
+//!
+//! The channel has a fixed number of priority levels chosen at construction, and the receiver
+//! always gets a value from the highest-priority level that has one available. Level `0` is the
+//! highest priority.
+//!
+//! # Performance
+//!
+//! - **Lock-free:** Every level is an independent [`mpsc`] queue, so senders of
+//!   different levels never contend on the same tail.
+//! - **Non-empty Bitmap:** A single padded word tracks which levels may hold values, so the receiver
+//!   finds the highest-priority non-empty level with one load and a `trailing_zeros`, instead of
+//!   polling every level.
+//!
+//! # When to use
+//!
+//! Use this queue when some messages must overtake others, e.g. control messages sharing a channel
+//! with a high volume of data messages. Ordering is FIFO within a level, but there is no ordering
+//! between messages of different levels.
+
+use core::num::NonZeroUsize;
+
+use crate::{Box, mpsc};
+
+pub use self::{receiver::Receiver, sender::Sender};
+
+mod levels;
+mod receiver;
+mod sender;
+
+/// Creates a new priority channel.
+///
+/// See the [module-level documentation](self) for more details on performance and usage.
+///
+/// # Arguments
+///
+/// * `levels` - The number of priority levels, at most [`usize::BITS`].
+/// * `capacity_per_level` - The capacity of the queue of each level.
+///
+/// # Returns
+///
+/// A tuple containing the [`Sender`] and [`Receiver`] handles.
+///
+/// # Panics
+///
+/// Panics if `levels` is greater than [`usize::BITS`].
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use gil::priority::channel;
+///
+/// let (mut tx, mut rx) =
+///     channel::<&str>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(1024).unwrap());
+///
+/// tx.send(1, "data");
+/// tx.send(0, "control");
+///
+/// assert_eq!(rx.recv(), "control");
+/// assert_eq!(rx.recv(), "data");
+/// ```
+pub fn channel<T>(
+    levels: NonZeroUsize,
+    capacity_per_level: NonZeroUsize,
+) -> (Sender<T>, Receiver<T>) {
+    assert!(
+        levels.get() <= usize::BITS as usize,
+        "number of priority levels must be <= {}",
+        usize::BITS
+    );
+
+    let mut senders = Box::new_uninit_slice(levels.get());
+    let mut receivers = Box::new_uninit_slice(levels.get());
+
+    for i in 0..levels.get() {
+        let (tx, rx) = mpsc::channel(capacity_per_level);
+        senders[i].write(tx);
+        receivers[i].write(rx);
+    }
+
+    let levels = levels::LevelsPtr::new();
+
+    // SAFETY: all elements were initialised in the loop above
+    unsafe {
+        (
+            Sender::new(senders.assume_init(), levels.clone()),
+            Receiver::new(receivers.assume_init(), levels),
+        )
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod test {
+    use super::*;
+
+    use crate::thread;
+
+    #[test]
+    fn basic() {
+        const THREADS: u32 = 8;
+        const ITER: u32 = 1000;
+
+        let (tx, mut rx) = channel(NonZeroUsize::new(4).unwrap(), NonZeroUsize::new(4).unwrap());
+
+        thread::scope(move |scope| {
+            for thread_id in 0..THREADS {
+                let mut tx = tx.clone();
+                scope.spawn(move || {
+                    for i in 0..ITER {
+                        tx.send(thread_id as usize % 4, (thread_id, i));
+                    }
+                });
+            }
+
+            let mut sum = 0;
+            for _ in 0..THREADS {
+                for _ in 0..ITER {
+                    let (_thread_id, i) = rx.recv();
+                    sum += i;
+                }
+            }
+
+            assert_eq!(sum, (ITER * (ITER - 1)) / 2 * THREADS);
+        });
+    }
+
+    #[test]
+    fn test_is_closed() {
+        let (mut tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        let tx2 = tx.clone();
+        tx.send(1, 0);
+        tx.send(0, 1);
+        drop(tx);
+        assert!(!rx.is_closed());
+        drop(tx2);
+        assert!(rx.is_closed());
+        assert!(rx.iter().eq([1, 0]));
+
+        let (tx, rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        drop(rx);
+        assert!(tx.is_closed());
+    }
+
+    #[test]
+    fn test_close() {
+        let (mut tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        tx.send(1, 0);
+        rx.close();
+        assert!(tx.is_closed() && rx.is_closed());
+        assert_eq!(tx.try_send(0, 1), Err(1));
+        tx.send(0, 2);
+        assert_eq!(rx.try_recv(), Some(0));
+        assert_eq!(rx.try_recv(), None);
+    }
+
+    #[test]
+    fn test_drain() {
+        let (mut tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(8).unwrap());
+        tx.send(1, 2);
+        tx.send(0, 0);
+        tx.send(1, 3);
+        tx.send(0, 1);
+        assert!(rx.drain().eq(0..4));
+
+        tx.send(1, 4);
+        tx.send(0, 5);
+        rx.clear();
+        assert!(rx.try_iter().next().is_none());
+    }
+
+    #[test]
+    fn test_priority_order() {
+        let (mut tx, mut rx) =
+            channel::<(usize, usize)>(NonZeroUsize::new(3).unwrap(), NonZeroUsize::new(8).unwrap());
+
+        for i in 0..4 {
+            tx.try_send(2, (2, i)).unwrap();
+            tx.try_send(1, (1, i)).unwrap();
+        }
+        tx.try_send(0, (0, 0)).unwrap();
+
+        assert_eq!(rx.try_recv(), Some((0, 0)));
+        for i in 0..4 {
+            assert_eq!(rx.try_recv(), Some((1, i)));
+        }
+
+        // a higher priority value overtakes the remaining lower priority ones
+        tx.try_send(0, (0, 1)).unwrap();
+        assert_eq!(rx.try_recv(), Some((0, 1)));
+
+        for i in 0..4 {
+            assert_eq!(rx.try_recv(), Some((2, i)));
+        }
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_clone_non_clone_values() {
+        struct NotClone(usize);
+
+        let (tx, mut rx) =
+            channel::<NotClone>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(4).unwrap());
+        let mut tx2 = tx.clone();
+        drop(tx);
+
+        tx2.send(1, NotClone(1));
+        tx2.send(0, NotClone(0));
+        assert_eq!(rx.recv().0, 0);
+        assert_eq!(rx.recv().0, 1);
+    }
+
+    #[test]
+    fn test_valid_try_sends() {
+        let (mut tx, mut rx) =
+            channel::<usize>(NonZeroUsize::new(2).unwrap(), NonZeroUsize::new(4).unwrap());
+        for _ in 0..4 {
+            assert!(rx.try_recv().is_none());
+        }
+        for i in 0..4 {
+            tx.try_send(1, i).unwrap();
+        }
+        assert!(tx.try_send(1, 5).is_err());
+        tx.try_send(0, 5).unwrap();
+
+        assert_eq!(rx.try_recv(), Some(5));
+        for i in 0..4 {
+            assert_eq!(rx.try_recv(), Some(i));
+        }
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_drop_full_capacity() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct DropCounter(Arc<AtomicUsize>);
+
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let dropped_count = Arc::new(AtomicUsize::new(0));
+
+        {
+            let (mut tx, _rx) = channel::<DropCounter>(
+                NonZeroUsize::new(2).unwrap(),
+                NonZeroUsize::new(4).unwrap(),
+            );
+
+            for i in 0..8 {
+                tx.send(i % 2, DropCounter(dropped_count.clone()));
+            }
+        }
+
+        let count = dropped_count.load(Ordering::SeqCst);
+        assert_eq!(
+            count, 8,
+            "Expected 8 items to be dropped, but got {}",
+            count
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{Backoff, Box, atomic::Ordering, mpsc, priority::levels::LevelsPtr};
+
+/// The consumer end of the priority channel.
+///
+/// This struct is `Send` but not `Sync`. It can be moved to another thread, but cannot be shared
+/// across threads.
+pub struct Receiver<T> {
+    receivers: Box<[mpsc::Receiver<T>]>,
+    levels: LevelsPtr,
+}
+
+impl<T> Receiver<T> {
+    pub(crate) fn new(receivers: Box<[mpsc::Receiver<T>]>, levels: LevelsPtr) -> Self {
+        Self { receivers, levels }
+    }
+
+    /// Returns the number of priority levels of the channel.
+    #[inline(always)]
+    pub fn levels(&self) -> usize {
+        self.receivers.len()
+    }
+
+    /// Closes the channel, so that sending fails from then on, without dropping the values
+    /// already in it.
+    ///
+    /// The values can still be received.
+    pub fn close(&mut self) {
+        for rx in &mut self.receivers {
+            rx.close();
+        }
+    }
+
+    /// Returns `true` if all the senders were dropped, or if the channel was closed with
+    /// [`Receiver::close`].
+    ///
+    /// The values sent before can still be received.
+    pub fn is_closed(&self) -> bool {
+        // every sender holds the sending ends of all the levels
+        self.receivers[0].is_closed()
+    }
+
+    /// Receives the highest-priority value available, blocking if necessary.
+    ///
+    /// This method uses a spin loop to wait for available data in the queue.
+    /// For a non-blocking alternative, use [`Receiver::try_recv`].
+    pub fn recv(&mut self) -> T {
+        let mut backoff = Backoff::with_spin_count(128);
+        loop {
+            match self.try_recv() {
+                None => backoff.backoff(),
+                Some(ret) => return ret,
+            }
+        }
+    }
+
+    /// Attempts to receive the highest-priority value available without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(value)` if a value is available.
+    /// * `None` if all levels are empty.
+    pub fn try_recv(&mut self) -> Option<T> {
+        let ready = self.levels.ready();
+
+        loop {
+            let mask = ready.load(Ordering::Acquire);
+            if mask == 0 {
+                return None;
+            }
+
+            let level = mask.trailing_zeros() as usize;
+            if let Some(ret) = self.receivers[level].try_recv() {
+                return Some(ret);
+            }
+
+            // the level looks empty, clear its bit and check again so that we don't lose a value
+            // published between our first look and the clear
+            let bit = 1 << level;
+            ready.fetch_and(!bit, Ordering::AcqRel);
+            if let Some(ret) = self.receivers[level].try_recv() {
+                ready.fetch_or(bit, Ordering::Release);
+                return Some(ret);
+            }
+        }
+    }
+
+    /// Returns an iterator receiving values without blocking, until all levels are empty.
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.try_recv())
+    }
+
+    /// Returns an iterator receiving values, blocking for each one until all the senders are
+    /// dropped and every level is empty.
+    ///
+    /// It also ends once the channel is closed with [`Receiver::close`] and emptied.
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        crate::iter::blocking(self, Self::try_recv, Self::is_closed)
+    }
+
+    /// Returns an iterator receiving the values available in the levels when it is called.
+    ///
+    /// The levels are drained one after the other, from the highest priority. Unlike
+    /// [`Receiver::try_iter`], it ends even if the senders keep sending. Values it didn't get to
+    /// because it was dropped early stay in the channel.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        // the ready bits of the drained levels are cleared by the next `try_recv`
+        self.receivers.iter_mut().flat_map(|rx| rx.drain())
+    }
+
+    /// Drops all the values available in the levels.
+    pub fn clear(&mut self) {
+        for rx in &mut self.receivers {
+            rx.clear();
+        }
+    }
+}
+
+unsafe impl<T: Send> Send for Receiver<T> {}
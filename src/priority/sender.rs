@@ -0,0 +1,84 @@
+use crate::{Box, atomic::Ordering, mpsc, priority::levels::LevelsPtr};
+
+/// The producer end of the priority channel.
+///
+/// This struct is `Clone` and `Send`. It can be shared across threads by cloning it.
+pub struct Sender<T> {
+    senders: Box<[mpsc::Sender<T>]>,
+    levels: LevelsPtr,
+}
+
+impl<T> Sender<T> {
+    pub(crate) fn new(senders: Box<[mpsc::Sender<T>]>, levels: LevelsPtr) -> Self {
+        Self { senders, levels }
+    }
+
+    /// Returns the number of priority levels of the channel.
+    #[inline(always)]
+    pub fn levels(&self) -> usize {
+        self.senders.len()
+    }
+
+    /// Returns `true` if the receiver closed the channel with [`Receiver::close`] or was dropped.
+    ///
+    /// Sending fails from then on. This can be used to cancel the work producing the values
+    /// early.
+    ///
+    /// [`Receiver::close`]: super::Receiver::close
+    pub fn is_closed(&self) -> bool {
+        // the receiver holds the receiving ends of all the levels
+        self.senders[0].is_closed()
+    }
+
+    /// Sends a value with the given priority, blocking if necessary.
+    ///
+    /// Level `0` is the highest priority. This method uses a spin loop to wait for available space
+    /// in the queue of that level. For a non-blocking alternative, use [`Sender::try_send`].
+    ///
+    /// If the channel is closed, the value is dropped and this returns right away.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `priority` is not less than [`Sender::levels`].
+    pub fn send(&mut self, priority: usize, value: T) {
+        self.senders[priority].send(value);
+        self.mark_ready(priority);
+    }
+
+    /// Attempts to send a value with the given priority without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the value was successfully sent.
+    /// * `Err(value)` if the queue of that level is full or the channel is closed, returning the
+    ///   original value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `priority` is not less than [`Sender::levels`].
+    pub fn try_send(&mut self, priority: usize, value: T) -> Result<(), T> {
+        self.senders[priority].try_send(value)?;
+        self.mark_ready(priority);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn mark_ready(&self, priority: usize) {
+        // has to be an RMW even if the bit is already set, so that a receiver clearing the bit
+        // concurrently is guaranteed to see the value we just published when it re-checks
+        self.levels
+            .ready()
+            .fetch_or(1 << priority, Ordering::Release);
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            senders: self.senders.clone(),
+            levels: self.levels.clone(),
+        }
+    }
+}
+
+unsafe impl<T: Send> Send for Sender<T> {}
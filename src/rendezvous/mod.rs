@@ -0,0 +1,221 @@
+//! Zero-capacity rendezvous channel.
+//!
+//! Unlike the other channels of this crate, this one doesn't buffer anything: a send only
+//! completes once the receiver has taken the value, so the producer and the consumer meet at every
+//! handoff.
+//!
+//! # Performance
+//!
+//! The channel is a single small allocation holding one value slot and one state word. Every
+//! handoff requires both sides to be there at the same time, so the throughput is bounded by the
+//! slower side, and each handoff costs a round trip of the state cache line between the two
+//! threads.
+//!
+//! # When to use
+//!
+//! Use this channel when the producer must not run ahead of the consumer, e.g. for stages of a
+//! lockstep simulation. If some slack between the two sides is acceptable, a small
+//! [`spsc`](crate::spsc) queue is much faster.
+
+pub use self::{receiver::Receiver, sender::Sender};
+
+mod receiver;
+mod sender;
+mod slot;
+
+/// Creates a new single-producer single-consumer rendezvous channel.
+///
+/// See the [module-level documentation](self) for more details on performance and usage.
+///
+/// # Returns
+///
+/// A tuple containing the [`Sender`] and [`Receiver`] handles.
+///
+/// # Examples
+///
+/// ```
+/// use gil::rendezvous::channel;
+///
+/// let (mut tx, mut rx) = channel::<usize>();
+///
+/// // nobody is waiting for the value
+/// assert_eq!(tx.try_send(1), Err(1));
+///
+/// let handle = std::thread::spawn(move || {
+///     // returns once the value is taken
+///     tx.send(2);
+/// });
+///
+/// assert_eq!(rx.recv(), 2);
+/// handle.join().unwrap();
+/// ```
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let slot = slot::SlotPtr::new();
+    (Sender::new(slot.clone()), Receiver::new(slot))
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod test {
+    use super::*;
+
+    use crate::thread;
+
+    #[test]
+    fn basic() {
+        const ITER: usize = 10_000;
+
+        let (mut tx, mut rx) = channel::<usize>();
+
+        thread::spawn(move || {
+            for i in 0..ITER {
+                tx.send(i);
+            }
+        });
+
+        for i in 0..ITER {
+            assert_eq!(rx.recv(), i);
+        }
+    }
+
+    #[test]
+    fn test_lockstep() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        const ITER: usize = 1000;
+
+        let (mut tx, mut rx) = channel::<usize>();
+        let taken = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for i in 0..ITER {
+                    tx.send(i);
+                    // the receiver can be at most one value ahead of what we sent
+                    assert!(taken.load(Ordering::SeqCst) <= i + 1);
+                }
+            });
+
+            for i in 0..ITER {
+                assert_eq!(rx.recv(), i);
+                taken.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    }
+
+    #[test]
+    fn test_try_send_try_recv() {
+        let (mut tx, mut rx) = channel::<usize>();
+
+        assert_eq!(tx.try_send(1), Err(1));
+        assert!(rx.try_recv().is_none());
+
+        thread::scope(|scope| {
+            scope.spawn(|| assert_eq!(rx.recv(), 2));
+
+            let mut value = 2;
+            while let Err(v) = tx.try_send(value) {
+                value = v;
+                core::hint::spin_loop();
+            }
+        });
+
+        thread::scope(|scope| {
+            scope.spawn(|| tx.send(3));
+
+            loop {
+                if let Some(value) = rx.try_recv() {
+                    assert_eq!(value, 3);
+                    break;
+                }
+                core::hint::spin_loop();
+            }
+        });
+    }
+
+    #[test]
+    fn test_is_closed() {
+        let (tx, rx) = channel::<usize>();
+        assert!(!tx.is_closed() && !rx.is_closed());
+        drop(rx);
+        assert!(tx.is_closed());
+
+        let (tx, mut rx) = channel::<usize>();
+        drop(tx);
+        assert!(rx.is_closed());
+        assert!(rx.iter().next().is_none());
+    }
+
+    #[test]
+    fn test_send_waiting_when_receiver_dropped() {
+        use std::sync::Arc;
+
+        let item = Arc::new(());
+        let (mut tx, rx) = channel::<Arc<()>>();
+        let waiting = {
+            let item = item.clone();
+            thread::spawn(move || {
+                tx.send(item);
+                tx
+            })
+        };
+        thread::sleep(core::time::Duration::from_millis(50));
+        drop(rx);
+
+        let mut tx = waiting.join().unwrap();
+        assert_eq!(Arc::strong_count(&item), 1);
+        assert!(tx.try_send(item.clone()).is_err());
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn test_drop_values() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct DropCounter(Arc<AtomicUsize>);
+
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let dropped_count = Arc::new(AtomicUsize::new(0));
+
+        {
+            let (mut tx, mut rx) = channel::<DropCounter>();
+            assert!(tx.try_send(DropCounter(dropped_count.clone())).is_err());
+            assert_eq!(dropped_count.load(Ordering::SeqCst), 1);
+
+            thread::scope(|scope| {
+                scope.spawn(|| tx.send(DropCounter(dropped_count.clone())));
+                drop(rx.recv());
+            });
+        }
+
+        assert_eq!(dropped_count.load(Ordering::SeqCst), 2);
+    }
+}
+
+#[cfg(all(test, feature = "loom"))]
+mod loom_test {
+    use super::*;
+    use crate::thread;
+
+    #[test]
+    fn basic_loom() {
+        loom::model(|| {
+            let (mut tx, mut rx) = channel::<usize>();
+
+            thread::spawn(move || {
+                tx.send(1);
+                if tx.try_send(2).is_err() {
+                    tx.send(2);
+                }
+            });
+
+            assert_eq!(rx.recv(), 1);
+            assert_eq!(rx.recv(), 2);
+        })
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{
+    Backoff,
+    atomic::Ordering,
+    rendezvous::slot::{EMPTY, FULL, SlotPtr, WAITING},
+};
+
+/// The consumer end of the rendezvous channel.
+///
+/// This struct is `Send` but not `Sync` or `Clone`. It can be moved to another thread, but cannot be shared
+/// across threads.
+pub struct Receiver<T> {
+    ptr: SlotPtr<T>,
+}
+
+impl<T> Receiver<T> {
+    pub(crate) fn new(slot_ptr: SlotPtr<T>) -> Self {
+        Self { ptr: slot_ptr }
+    }
+
+    /// Returns `true` if the sender was dropped, in which case [`Receiver::recv`] would block
+    /// forever.
+    pub fn is_closed(&self) -> bool {
+        self.ptr.is_unique()
+    }
+
+    /// Receives a value, blocking until a sender offers one.
+    ///
+    /// This method uses a spin loop to wait for a value, and marks the receiver as waiting so that
+    /// [`Sender::try_send`](super::Sender::try_send) can succeed in the meantime.
+    /// For a non-blocking alternative, use [`Receiver::try_recv`].
+    pub fn recv(&mut self) -> T {
+        let state = self.ptr.state();
+
+        // fails if a value is already offered, which is fine
+        _ = state.compare_exchange(EMPTY, WAITING, Ordering::Relaxed, Ordering::Relaxed);
+
+        let mut backoff = Backoff::with_spin_count(128);
+        while state.load(Ordering::Acquire) != FULL {
+            backoff.backoff();
+        }
+
+        self.take()
+    }
+
+    /// Attempts to receive a value without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(value)` if a sender is blocked in [`Sender::send`](super::Sender::send) offering a
+    ///   value.
+    /// * `None` otherwise.
+    pub fn try_recv(&mut self) -> Option<T> {
+        if self.ptr.state().load(Ordering::Acquire) != FULL {
+            return None;
+        }
+
+        Some(self.take())
+    }
+
+    /// Returns an iterator receiving values without blocking, as long as senders are offering
+    /// them.
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.try_recv())
+    }
+
+    /// Returns an iterator receiving values, blocking for each one until the sender is dropped.
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        crate::iter::blocking(self, Self::try_recv, Self::is_closed)
+    }
+
+    /// Returns an iterator receiving the value offered by a sender when it is called, if any.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.try_recv().into_iter()
+    }
+
+    /// Drops the value offered by a sender, if any.
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+
+    /// Must only be called after observing the state as `FULL`.
+    #[inline(always)]
+    fn take(&mut self) -> T {
+        // SAFETY: state is `FULL`, so the value is initialised and it's ours to take
+        let ret = unsafe { self.ptr.read() };
+        // releases the sender blocked on this value
+        self.ptr.state().store(EMPTY, Ordering::Release);
+        ret
+    }
+}
+
+unsafe impl<T: Send> Send for Receiver<T> {}
@@ -0,0 +1,92 @@
+use crate::{
+    Backoff,
+    atomic::Ordering,
+    rendezvous::slot::{EMPTY, FULL, SlotPtr, WAITING},
+};
+
+/// The producer end of the rendezvous channel.
+///
+/// This struct is `Send` but not `Sync` or `Clone`. It can be moved to another thread, but cannot be shared
+/// across threads.
+pub struct Sender<T> {
+    ptr: SlotPtr<T>,
+}
+
+impl<T> Sender<T> {
+    pub(crate) fn new(slot_ptr: SlotPtr<T>) -> Self {
+        Self { ptr: slot_ptr }
+    }
+
+    /// Returns `true` if the receiver was dropped, in which case sending fails.
+    pub fn is_closed(&self) -> bool {
+        self.ptr.is_unique()
+    }
+
+    /// Sends a value, blocking until the receiver has taken it.
+    ///
+    /// This method uses a spin loop to wait for the receiver.
+    /// For a non-blocking alternative, use [`Sender::try_send`].
+    ///
+    /// If the receiver is dropped before taking the value, the value is dropped and this returns.
+    pub fn send(&mut self, value: T) {
+        // SAFETY: the previous send waited for its value to be taken, so the slot isn't `FULL`
+        unsafe { self.ptr.write(value) };
+
+        // the receiver may be moving the state from `EMPTY` to `WAITING` concurrently, either way
+        // it's going to see the value
+        self.ptr.state().swap(FULL, Ordering::AcqRel);
+
+        _ = self.wait_taken();
+    }
+
+    /// Attempts to hand a value over to the receiver without blocking.
+    ///
+    /// This only succeeds if the receiver is currently blocked in
+    /// [`Receiver::recv`](super::Receiver::recv), in which case this waits the short time it
+    /// takes for the receiver to take the value.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the value was taken by the receiver.
+    /// * `Err(value)` if the receiver isn't waiting or was dropped, returning the original value.
+    pub fn try_send(&mut self, value: T) -> Result<(), T> {
+        // SAFETY: the previous send waited for its value to be taken, so the slot isn't `FULL`
+        unsafe { self.ptr.write(value) };
+
+        if self
+            .ptr
+            .state()
+            .compare_exchange(WAITING, FULL, Ordering::AcqRel, Ordering::Relaxed)
+            .is_err()
+        {
+            // SAFETY: the value wasn't offered, so it is still ours
+            return Err(unsafe { self.ptr.read() });
+        }
+
+        self.wait_taken()
+    }
+
+    /// Waits for the receiver to take the offered value, or takes it back if the receiver is
+    /// dropped first.
+    #[inline(always)]
+    fn wait_taken(&self) -> Result<(), T> {
+        let mut backoff = Backoff::with_spin_count(128);
+        while self.ptr.state().load(Ordering::Acquire) == FULL {
+            if self.ptr.is_unique() {
+                // the receiver may have taken the value right before being dropped
+                if self.ptr.state().load(Ordering::Acquire) != FULL {
+                    break;
+                }
+
+                // SAFETY: the value wasn't taken, and nobody else can take it anymore
+                let value = unsafe { self.ptr.read() };
+                self.ptr.state().store(EMPTY, Ordering::Relaxed);
+                return Err(value);
+            }
+            backoff.backoff();
+        }
+        Ok(())
+    }
+}
+
+unsafe impl<T: Send> Send for Sender<T> {}
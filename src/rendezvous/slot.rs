@@ -0,0 +1,94 @@
+use core::{mem::MaybeUninit, ptr::NonNull};
+
+use crate::{
+    Box,
+    atomic::{AtomicUsize, Ordering},
+};
+
+/// No value is offered, and the receiver isn't waiting for one.
+pub(crate) const EMPTY: usize = 0;
+/// The receiver is blocked in `recv`, waiting for a value.
+pub(crate) const WAITING: usize = 1;
+/// The sender offered a value, and the receiver hasn't taken it yet.
+pub(crate) const FULL: usize = 2;
+
+/// # Invariants
+/// - `value` is initialised iff `state` is `FULL`.
+/// - only the sender moves `state` to `FULL`, and only the receiver moves it out of `FULL`, unless
+///   the receiver is gone and the sender takes its value back.
+// avoid re-ordering fields
+#[repr(C)]
+struct Slot<T> {
+    state: AtomicUsize,
+    rc: AtomicUsize,
+    value: MaybeUninit<T>,
+}
+
+pub(crate) struct SlotPtr<T> {
+    ptr: NonNull<Slot<T>>,
+}
+
+impl<T> Clone for SlotPtr<T> {
+    fn clone(&self) -> Self {
+        self.rc().fetch_add(1, Ordering::AcqRel);
+        Self { ptr: self.ptr }
+    }
+}
+
+impl<T> SlotPtr<T> {
+    pub(crate) fn new() -> Self {
+        let ptr = Box::into_raw(Box::new(Slot {
+            state: AtomicUsize::new(EMPTY),
+            rc: AtomicUsize::new(1),
+            value: MaybeUninit::uninit(),
+        }));
+
+        // SAFETY: `Box::into_raw` never returns null
+        Self {
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn state(&self) -> &AtomicUsize {
+        unsafe { _field!(Slot<T>, self.ptr, state, AtomicUsize).as_ref() }
+    }
+
+    #[inline(always)]
+    fn rc(&self) -> &AtomicUsize {
+        unsafe { _field!(Slot<T>, self.ptr, rc, AtomicUsize).as_ref() }
+    }
+
+    /// Whether the other end is gone, neither end being `Clone`.
+    #[inline(always)]
+    pub(crate) fn is_unique(&self) -> bool {
+        self.rc().load(Ordering::Acquire) == 1
+    }
+
+    /// # Safety
+    /// Only the sender may call this, and only while `state` is not `FULL`.
+    #[inline(always)]
+    pub(crate) unsafe fn write(&self, value: T) {
+        unsafe { _field!(Slot<T>, self.ptr, value, T).write(value) }
+    }
+
+    /// # Safety
+    /// The value must be initialised, and must not be read again afterwards.
+    #[inline(always)]
+    pub(crate) unsafe fn read(&self) -> T {
+        unsafe { _field!(Slot<T>, self.ptr, value, T).read() }
+    }
+}
+
+impl<T> Drop for SlotPtr<T> {
+    fn drop(&mut self) {
+        if self.rc().fetch_sub(1, Ordering::AcqRel) == 1 {
+            if self.state().load(Ordering::Relaxed) == FULL {
+                unsafe { drop(self.read()) };
+            }
+
+            // creating a box so that heap allocation is also freed
+            _ = unsafe { Box::from_raw(self.ptr.as_ptr()) };
+        }
+    }
+}
@@ -0,0 +1,528 @@
+//! Waiting on multiple channel operations at once.
+//!
+//! A [`Select`] holds a set of receive and send operations over any of the channel handles of this
+//! crate and blocks until one of them completes. The [`select!`](crate::select!) macro does the same
+//! without allocating, and lets each arm run arbitrary code (including `return`, `break` and `?`)
+//! with the result of the operation that completed.
+//!
+//! Waiting uses the same spin-then-yield [`Backoff`] as the blocking `recv`/`send` methods.
+//!
+//! # Examples
+//!
+//! ```
+//! use core::num::NonZeroUsize;
+//! use gil::{mpsc, select::Select, spsc};
+//!
+//! let (mut data_tx, mut data_rx) = mpsc::channel::<u64>(NonZeroUsize::new(16).unwrap());
+//! let (mut ctrl_tx, mut ctrl_rx) = spsc::channel::<&str>(NonZeroUsize::new(16).unwrap());
+//!
+//! enum Event {
+//!     Data(u64),
+//!     Control(&'static str),
+//! }
+//!
+//! data_tx.send(42);
+//! ctrl_tx.send("stop");
+//!
+//! let mut sel = Select::new();
+//! sel.recv(&mut data_rx, Event::Data);
+//! sel.recv(&mut ctrl_rx, Event::Control);
+//!
+//! let mut seen = 0;
+//! while seen < 2 {
+//!     match sel.select() {
+//!         Event::Data(value) => assert_eq!(value, 42),
+//!         Event::Control(msg) => assert_eq!(msg, "stop"),
+//!     }
+//!     seen += 1;
+//! }
+//! ```
+
+use alloc_crate::vec::Vec;
+
+use crate::{Allocator, Backoff, Box, Capacity, CellLayout};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A channel handle that values can be received from without blocking.
+///
+/// This trait is sealed and implemented for all the receivers of this crate.
+pub trait TryRecv: sealed::Sealed {
+    /// The type of the values received.
+    type Item;
+
+    /// Attempts to receive a value without blocking.
+    fn try_recv(&mut self) -> Option<Self::Item>;
+}
+
+/// A channel handle that values can be sent into without blocking.
+///
+/// This trait is sealed and implemented for all the senders of this crate that don't need
+/// anything besides the value to send it.
+pub trait TrySend: sealed::Sealed {
+    /// The type of the values sent.
+    type Item;
+
+    /// Attempts to send a value without blocking, returning it back if there is no space.
+    fn try_send(&mut self, value: Self::Item) -> Result<(), Self::Item>;
+}
+
+macro_rules! impl_try_recv {
+    ($([$($generics:tt)*] $ty:ty),* $(,)?) => {
+        $(
+            impl<T, $($generics)*> sealed::Sealed for $ty {}
+
+            impl<T, $($generics)*> TryRecv for $ty {
+                type Item = T;
+
+                #[inline(always)]
+                fn try_recv(&mut self) -> Option<T> {
+                    <$ty>::try_recv(self)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_try_send {
+    ($([$($generics:tt)*] $ty:ty),* $(,)?) => {
+        $(
+            impl<T, $($generics)*> sealed::Sealed for $ty {}
+
+            impl<T, $($generics)*> TrySend for $ty {
+                type Item = T;
+
+                #[inline(always)]
+                fn try_send(&mut self, value: T) -> Result<(), T> {
+                    <$ty>::try_send(self, value)
+                }
+            }
+        )*
+    };
+}
+
+impl_try_recv!(
+    [A: Allocator, C: Capacity] crate::spsc::Receiver<T, A, C>,
+    [A: Allocator, C: Capacity, L: CellLayout] crate::mpsc::Receiver<T, A, C, L>,
+    [A: Allocator, C: Capacity, L: CellLayout] crate::mpmc::Receiver<T, A, C, L>,
+    [A: Allocator, C: Capacity, L: CellLayout] crate::spmc::Receiver<T, A, C, L>,
+    [A: Allocator + Clone] crate::mpsc::sharded::Receiver<T, A>,
+    [A: Allocator + Clone] crate::mpmc::sharded::Receiver<T, A>,
+    [] crate::priority::Receiver<T>,
+    [] crate::rendezvous::Receiver<T>,
+);
+
+impl_try_send!(
+    [A: Allocator, C: Capacity] crate::spsc::Sender<T, A, C>,
+    [A: Allocator, C: Capacity, L: CellLayout] crate::mpsc::Sender<T, A, C, L>,
+    [A: Allocator, C: Capacity, L: CellLayout] crate::mpmc::Sender<T, A, C, L>,
+    [A: Allocator, C: Capacity, L: CellLayout] crate::spmc::Sender<T, A, C, L>,
+    [A: Allocator + Clone] crate::mpsc::sharded::Sender<T, A>,
+    [A: Allocator + Clone] crate::mpmc::sharded::Sender<T, A>,
+    [] crate::rendezvous::Sender<T>,
+);
+
+type Operation<'a, R> = Box<dyn FnMut() -> Option<R> + 'a>;
+
+/// A set of channel operations to wait on.
+///
+/// Operations are registered with [`Select::recv`] and [`Select::send`], each with a closure
+/// mapping its outcome to a common result type `R`. [`Select::select`] then blocks until one of the
+/// operations completes and returns the result of its closure.
+///
+/// A `Select` can be reused for as many selections as needed. Receive operations stay registered
+/// forever, while a send operation is done once its value has been sent. Ready operations are
+/// picked in a round-robin fashion, so a busy channel can't starve the others.
+pub struct Select<'a, R> {
+    operations: Vec<Operation<'a, R>>,
+    next: usize,
+}
+
+impl<'a, R> Default for Select<'a, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, R> Select<'a, R> {
+    /// Creates an empty set of operations.
+    pub fn new() -> Self {
+        Self {
+            operations: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Registers a receive operation on `rx`.
+    ///
+    /// When a value is received, it is passed to `f` and its result is returned from the
+    /// selection.
+    ///
+    /// # Returns
+    ///
+    /// The index of the operation, in the order of registration.
+    pub fn recv<Rx: TryRecv>(
+        &mut self,
+        rx: &'a mut Rx,
+        mut f: impl FnMut(Rx::Item) -> R + 'a,
+    ) -> usize {
+        self.push(Box::new(move || rx.try_recv().map(&mut f)))
+    }
+
+    /// Registers an operation sending `value` into `tx`.
+    ///
+    /// Once the value is sent, the result of `f` is returned from the selection and the operation
+    /// is never selected again.
+    ///
+    /// # Returns
+    ///
+    /// The index of the operation, in the order of registration.
+    pub fn send<Tx: TrySend>(
+        &mut self,
+        tx: &'a mut Tx,
+        value: Tx::Item,
+        f: impl FnOnce() -> R + 'a,
+    ) -> usize
+    where
+        Tx::Item: 'a,
+    {
+        let mut pending = Some((value, f));
+        self.push(Box::new(move || {
+            let (value, f) = pending.take()?;
+            match tx.try_send(value) {
+                Ok(()) => Some(f()),
+                Err(value) => {
+                    pending = Some((value, f));
+                    None
+                }
+            }
+        }))
+    }
+
+    /// Attempts to complete one of the operations without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(result)` with the result of the operation that completed.
+    /// * `None` if none of the operations could complete.
+    pub fn try_select(&mut self) -> Option<R> {
+        let len = self.operations.len();
+        for i in 0..len {
+            let idx = (self.next + i) % len;
+            if let Some(ret) = (self.operations[idx])() {
+                self.next = (idx + 1) % len;
+                return Some(ret);
+            }
+        }
+
+        None
+    }
+
+    /// Blocks until one of the operations completes.
+    ///
+    /// This method uses a spin loop to wait for one of the operations to be ready.
+    /// For a non-blocking alternative, use [`Select::try_select`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if no operations are registered, as it would block forever otherwise.
+    pub fn select(&mut self) -> R {
+        assert!(!self.operations.is_empty(), "no operations to select on");
+
+        let mut backoff = Backoff::with_spin_count(128);
+        loop {
+            match self.try_select() {
+                None => backoff.backoff(),
+                Some(ret) => return ret,
+            }
+        }
+    }
+
+    fn push(&mut self, operation: Operation<'a, R>) -> usize {
+        self.operations.push(operation);
+        self.operations.len() - 1
+    }
+}
+
+/// Blocks until one of several channel operations completes, and runs the code of its arm.
+///
+/// Each arm is one of:
+///
+/// * `recv(rx) -> pattern => body` - receives a value from `rx` and matches it against `pattern`.
+/// * `send(tx, value) => body` - sends `value` into `tx`.
+/// * `default => body` - runs if no other operation can complete right away. It must be the last
+///   arm, and makes the whole `select!` non-blocking.
+///
+/// Any handle with a `try_recv` or `try_send` method can be used. The arms are attempted in
+/// order, so earlier arms take precedence when several are ready. Values of `send` arms that
+/// didn't complete are dropped.
+///
+/// Waiting uses the same spin-then-yield [`Backoff`] as the blocking `recv`/`send` methods.
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use gil::{select, spsc};
+///
+/// let (mut tx1, mut rx1) = spsc::channel::<u32>(NonZeroUsize::new(4).unwrap());
+/// let (mut tx2, mut rx2) = spsc::channel::<u32>(NonZeroUsize::new(4).unwrap());
+///
+/// tx2.send(7);
+///
+/// let value = select! {
+///     recv(rx1) -> v => v,
+///     recv(rx2) -> v => v * 2,
+/// };
+/// assert_eq!(value, 14);
+///
+/// let sent = select! {
+///     send(tx1, 1) => true,
+///     default => false,
+/// };
+/// assert!(sent);
+/// assert_eq!(rx1.recv(), 1);
+/// ```
+#[macro_export]
+macro_rules! select {
+    ($($arms:tt)+) => {
+        $crate::__select!(@parse [] $($arms)+)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __select {
+    // every arm is normalised to `{ kind, result, [args], pattern, body }`, where `result` is a
+    // fresh `Option` the loop stores the outcome of the operation in. The bodies only run after
+    // the loop, so that `break` and `continue` in them refer to the caller's loops.
+    (@parse [$($arms:tt)*] recv($rx:expr) -> $pat:pat => $body:block $(,)? $($rest:tt)*) => {
+        $crate::__select!(@parse [$($arms)* { recv, __result, [$rx], $pat, $body }] $($rest)*)
+    };
+    (@parse [$($arms:tt)*] recv($rx:expr) -> $pat:pat => $body:expr $(, $($rest:tt)*)?) => {
+        $crate::__select!(@parse [$($arms)* { recv, __result, [$rx], $pat, $body }] $($($rest)*)?)
+    };
+    (@parse [$($arms:tt)*] send($tx:expr, $value:expr) => $body:block $(,)? $($rest:tt)*) => {
+        $crate::__select!(
+            @parse [$($arms)* { send, __result, [$tx, $value, __pending], (), $body }] $($rest)*
+        )
+    };
+    (@parse [$($arms:tt)*] send($tx:expr, $value:expr) => $body:expr $(, $($rest:tt)*)?) => {
+        $crate::__select!(
+            @parse [$($arms)* { send, __result, [$tx, $value, __pending], (), $body }]
+            $($($rest)*)?
+        )
+    };
+    (@parse [$($arms:tt)*] default => $body:expr $(,)?) => {
+        $crate::__select!(@emit [$($arms)* { default, __result, [], (), $body }])
+    };
+    (@parse [$($arms:tt)*]) => {
+        $crate::__select!(@emit [$($arms)* { block, __result, [], (), ::core::unreachable!() }])
+    };
+
+    (@emit [$({ $kind:ident, $result:ident, [$($args:tt)*], $pat:pat, $body:expr })+]) => {{
+        $( $crate::__select!(@setup __backoff, $kind, $result, [$($args)*]); )+
+
+        'select: loop {
+            $( $crate::__select!(@poll 'select, __backoff, $kind, $result, [$($args)*]); )+
+        }
+
+        $( if let ::core::option::Option::Some($pat) = $result { $body } else )+ {
+            ::core::unreachable!()
+        }
+    }};
+
+    (@setup $backoff:ident, recv, $result:ident, [$rx:expr]) => {
+        let mut $result = ::core::option::Option::None;
+    };
+    (@setup $backoff:ident, send, $result:ident, [$tx:expr, $value:expr, $pending:ident]) => {
+        let mut $result = ::core::option::Option::None;
+        let mut $pending = ::core::option::Option::Some($value);
+    };
+    (@setup $backoff:ident, default, $result:ident, []) => {
+        let mut $result: ::core::option::Option<()> = ::core::option::Option::None;
+    };
+    (@setup $backoff:ident, block, $result:ident, []) => {
+        let $result: ::core::option::Option<()> = ::core::option::Option::None;
+        let mut $backoff = $crate::Backoff::with_spin_count(128);
+    };
+
+    (@poll $label:lifetime, $backoff:ident, recv, $result:ident, [$rx:expr]) => {
+        if let ::core::option::Option::Some(value) = ($rx).try_recv() {
+            $result = ::core::option::Option::Some(value);
+            break $label;
+        }
+    };
+    (@poll $label:lifetime, $backoff:ident, send, $result:ident, [$tx:expr, $value:expr, $pending:ident]) => {
+        if let ::core::option::Option::Some(value) = $pending.take() {
+            match ($tx).try_send(value) {
+                ::core::result::Result::Ok(()) => {
+                    $result = ::core::option::Option::Some(());
+                    break $label;
+                }
+                ::core::result::Result::Err(value) => {
+                    $pending = ::core::option::Option::Some(value);
+                }
+            }
+        }
+    };
+    (@poll $label:lifetime, $backoff:ident, default, $result:ident, []) => {
+        $result = ::core::option::Option::Some(());
+        break $label;
+    };
+    (@poll $label:lifetime, $backoff:ident, block, $result:ident, []) => {
+        $backoff.backoff();
+    };
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod test {
+    use core::num::NonZeroUsize;
+
+    use super::*;
+    use crate::{mpmc, mpsc, spsc, thread};
+
+    #[test]
+    fn basic() {
+        const ITER: usize = 1000;
+
+        let (mut tx1, mut rx1) = spsc::channel::<usize>(NonZeroUsize::new(4).unwrap());
+        let (tx2, mut rx2) = mpsc::channel::<usize>(NonZeroUsize::new(4).unwrap());
+
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                for i in 0..ITER {
+                    tx1.send(i);
+                }
+            });
+            for _ in 0..2 {
+                let mut tx2 = tx2.clone();
+                scope.spawn(move || {
+                    for i in 0..ITER {
+                        tx2.send(i);
+                    }
+                });
+            }
+
+            let mut sel = Select::new();
+            sel.recv(&mut rx1, |v| (0, v));
+            sel.recv(&mut rx2, |v| (1, v));
+
+            let mut sums = [0, 0];
+            for _ in 0..3 * ITER {
+                let (idx, v) = sel.select();
+                sums[idx] += v;
+            }
+
+            assert_eq!(sums[0], ITER * (ITER - 1) / 2);
+            assert_eq!(sums[1], ITER * (ITER - 1));
+        });
+    }
+
+    #[test]
+    fn test_send_operations() {
+        let (mut tx1, mut rx1) = mpmc::channel::<usize>(NonZeroUsize::new(2).unwrap());
+        let (mut tx2, mut rx2) = spsc::channel::<usize>(NonZeroUsize::new(1).unwrap());
+        tx1.send(0);
+
+        {
+            let mut sel = Select::new();
+            assert_eq!(sel.send(&mut tx1, 1, || 0), 0);
+            assert_eq!(sel.send(&mut tx2, 2, || 1), 1);
+
+            let first = sel.select();
+            let second = sel.select();
+            assert_eq!(first + second, 1);
+
+            // both values are sent and both queues are full
+            assert!(sel.try_select().is_none());
+        }
+
+        assert!(tx1.try_send(3).is_err());
+        assert_eq!(rx1.try_recv(), Some(0));
+        assert_eq!(rx1.try_recv(), Some(1));
+        assert_eq!(rx2.try_recv(), Some(2));
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let (mut tx1, mut rx1) = spsc::channel::<usize>(NonZeroUsize::new(8).unwrap());
+        let (mut tx2, mut rx2) = spsc::channel::<usize>(NonZeroUsize::new(8).unwrap());
+        for i in 0..4 {
+            tx1.send(i);
+            tx2.send(i);
+        }
+
+        let mut sel = Select::new();
+        sel.recv(&mut rx1, |_| 0);
+        sel.recv(&mut rx2, |_| 1);
+
+        for _ in 0..4 {
+            assert_eq!(sel.try_select(), Some(0));
+            assert_eq!(sel.try_select(), Some(1));
+        }
+        assert!(sel.try_select().is_none());
+    }
+
+    #[test]
+    fn test_macro() {
+        let (mut tx1, mut rx1) = spsc::channel::<usize>(NonZeroUsize::new(2).unwrap());
+        let (mut tx2, mut rx2) = mpsc::channel::<usize>(NonZeroUsize::new(2).unwrap());
+
+        let ret = select! {
+            recv(rx1) -> v => v,
+            recv(rx2) -> v => v,
+            default => usize::MAX,
+        };
+        assert_eq!(ret, usize::MAX);
+
+        tx2.send(2);
+        let ret = select! {
+            recv(rx1) -> v => v,
+            recv(rx2) -> v => v + 10,
+        };
+        assert_eq!(ret, 12);
+
+        let mut sent = 0;
+        while sent < 2 {
+            select! {
+                send(tx1, sent) => { sent += 1; }
+                default => break,
+            }
+        }
+        assert!(select! { send(tx1, 5) => false, default => true });
+
+        let received: usize = (0..2).map(|_| select! { recv(rx1) -> v => v }).sum();
+        assert_eq!(received, 1);
+    }
+
+    #[test]
+    fn test_macro_blocking() {
+        let (mut tx, mut rx) = spsc::channel::<usize>(NonZeroUsize::new(1).unwrap());
+        let (mut done_tx, mut done_rx) = spsc::channel::<()>(NonZeroUsize::new(1).unwrap());
+
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                for i in 0..100 {
+                    tx.send(i);
+                }
+                done_tx.send(());
+            });
+
+            let mut sum = 0;
+            loop {
+                select! {
+                    recv(rx) -> v => sum += v,
+                    recv(done_rx) -> () => break,
+                }
+            }
+            while let Some(v) = rx.try_recv() {
+                sum += v;
+            }
+
+            assert_eq!(sum, 100 * 99 / 2);
+        });
+    }
+}
@@ -0,0 +1,170 @@
+//! File-backed shared-memory mappings for the inter-process queues.
+
+use core::{
+    mem::{align_of, size_of},
+    ptr::NonNull,
+};
+use std::{fs::OpenOptions, io, os::fd::AsRawFd, path::Path};
+
+use crate::atomic::{AtomicU32, Ordering};
+
+/// A shared, read-write mapping of a whole file. Unmapped on drop, the file itself is left alone.
+pub(crate) struct Mapping {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+impl Mapping {
+    /// Creates a new file of `len` bytes at `path` and maps it. Fails if the file already exists.
+    pub(crate) fn create(path: &Path, len: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        file.set_len(len as u64)?;
+
+        Self::map(&file, len)
+    }
+
+    /// Maps the existing file at `path`, which must be at least `min_len` bytes long.
+    pub(crate) fn open(path: &Path, min_len: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let len = usize::try_from(file.metadata()?.len()).map_err(|_| invalid("file too large"))?;
+        if len < min_len {
+            return Err(invalid("file too small for the queue header"));
+        }
+
+        Self::map(&file, len)
+    }
+
+    fn map(file: &std::fs::File, len: usize) -> io::Result<Self> {
+        // SAFETY: mapping a file we have open for reading and writing
+        let ptr = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            // SAFETY: mmap never returns null on success when not asked for a fixed address
+            ptr: unsafe { NonNull::new_unchecked(ptr.cast()) },
+            len,
+        })
+    }
+
+    /// Page aligned start of the mapping.
+    #[inline(always)]
+    pub(crate) fn as_ptr(&self) -> NonNull<u8> {
+        self.ptr
+    }
+
+    #[inline(always)]
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.len) };
+    }
+}
+
+// SAFETY: the mapping itself is just an address range, accesses to its contents are synchronised
+// by the queues built on top of it
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+/// The first fields of every shared queue header, describing its layout so that a process opening
+/// the queue can check it was created for the same element type and version of the crate.
+///
+/// # Invariants
+/// - everything is immutable once `initialised` is set.
+// avoid re-ordering fields, the layout is shared between processes
+#[repr(C)]
+pub(crate) struct Preamble {
+    magic: u64,
+    version: u32,
+    initialised: AtomicU32,
+    header_size: u64,
+    buffer_offset: u64,
+    elem_size: u64,
+    elem_align: u64,
+    capacity: u64,
+}
+
+impl Preamble {
+    const VERSION: u32 = 1;
+
+    pub(crate) fn new<T>(
+        magic: u64,
+        header_size: usize,
+        buffer_offset: usize,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            magic,
+            version: Self::VERSION,
+            initialised: AtomicU32::new(0),
+            header_size: header_size as u64,
+            buffer_offset: buffer_offset as u64,
+            elem_size: size_of::<T>() as u64,
+            elem_align: align_of::<T>() as u64,
+            capacity: capacity as u64,
+        }
+    }
+
+    /// Marks the header as fully written, must be the last write of the creator.
+    pub(crate) fn set_initialised(&self) {
+        self.initialised.store(1, Ordering::Release);
+    }
+
+    /// Checks that the header was initialised for a queue of `T` with the given `magic` and header
+    /// size, and returns its capacity along with its buffer offset.
+    pub(crate) fn check<T>(&self, magic: u64, header_size: usize) -> io::Result<(usize, usize)> {
+        if self.initialised.load(Ordering::Acquire) == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "queue is not initialised yet",
+            ));
+        }
+        if self.magic != magic || self.version != Self::VERSION {
+            return Err(invalid("not a queue of this kind and version of gil"));
+        }
+        if self.header_size != header_size as u64 {
+            return Err(invalid("queue header layout mismatch"));
+        }
+        if self.elem_size != size_of::<T>() as u64 || self.elem_align != align_of::<T>() as u64 {
+            return Err(invalid("queue element layout mismatch"));
+        }
+
+        let capacity = usize::try_from(self.capacity).map_err(|_| invalid("invalid capacity"))?;
+        if !capacity.is_power_of_two() {
+            return Err(invalid("invalid queue capacity"));
+        }
+
+        Ok((capacity, self.buffer_offset as usize))
+    }
+}
+
+pub(crate) fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+pub(crate) fn temp_path() -> std::path::PathBuf {
+    use std::sync::atomic::AtomicUsize;
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(std::format!("gil-shm-test-{}-{n}", std::process::id()))
+}
@@ -1,18 +1,38 @@
-use crate::{atomic::Ordering, spmc::queue::QueuePtr};
+use crate::{
+    Allocator, Capacity, CellLayout, Dynamic, Global, PaddedCells, atomic::Ordering,
+    spmc::queue::QueuePtr,
+};
 
-pub struct Receiver<T> {
-    ptr: QueuePtr<T>,
+pub struct Receiver<T, A: Allocator = Global, C: Capacity = Dynamic, L: CellLayout = PaddedCells> {
+    ptr: QueuePtr<T, A, C, L>,
     local_head: usize,
 }
 
-impl<T> Receiver<T> {
-    pub(crate) fn new(queue_ptr: QueuePtr<T>) -> Self {
+impl<T, A: Allocator, C: Capacity, L: CellLayout> Receiver<T, A, C, L> {
+    pub(crate) fn new(queue_ptr: QueuePtr<T, A, C, L>) -> Self {
+        queue_ptr.receivers().fetch_add(1, Ordering::AcqRel);
         Self {
             ptr: queue_ptr,
             local_head: 0,
         }
     }
 
+    /// Closes the queue, so that sending fails from then on, without dropping the values already
+    /// in it.
+    ///
+    /// The values can still be received, by this receiver and its clones alike.
+    pub fn close(&mut self) {
+        self.ptr.closed().store(true, Ordering::Release);
+    }
+
+    /// Returns `true` if the sender was dropped, or if the queue was closed with
+    /// [`Receiver::close`].
+    ///
+    /// The values sent before can still be received.
+    pub fn is_closed(&self) -> bool {
+        self.ptr.senders().load(Ordering::Acquire) == 0 || self.ptr.closed().load(Ordering::Acquire)
+    }
+
     pub fn recv(&mut self) -> T {
         let head = self.ptr.head().fetch_add(1, Ordering::Relaxed);
         let next_head = head.wrapping_add(1);
@@ -25,7 +45,7 @@ impl<T> Receiver<T> {
 
         let ret = unsafe { cell.get() };
         cell.epoch()
-            .store(head.wrapping_add(self.ptr.capacity), Ordering::Release);
+            .store(head.wrapping_add(self.ptr.capacity()), Ordering::Release);
 
         ret
     }
@@ -51,7 +71,7 @@ impl<T> Receiver<T> {
                         Ok(_) => {
                             let ret = unsafe { cell.get() };
                             cell.epoch().store(
-                                self.local_head.wrapping_add(self.ptr.capacity),
+                                self.local_head.wrapping_add(self.ptr.capacity()),
                                 Ordering::Release,
                             );
                             self.local_head = next_head;
@@ -66,10 +86,39 @@ impl<T> Receiver<T> {
             backoff.backoff();
         }
     }
+
+    /// Returns an iterator receiving values without blocking, until the queue is empty.
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.try_recv())
+    }
+
+    /// Returns an iterator receiving values, blocking for each one until the sender is dropped and
+    /// the queue is empty.
+    ///
+    /// It also ends once the channel is closed with [`Receiver::close`] and emptied.
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        crate::iter::blocking(self, Self::try_recv, Self::is_closed)
+    }
+
+    /// Returns an iterator receiving the values available in the queue when it is called.
+    ///
+    /// Unlike [`Receiver::try_iter`], it ends even if the senders keep sending. It yields at most
+    /// as many values as the queue holds, some of which may have been sent after the call. Values
+    /// it didn't get to because it was dropped early stay in the queue.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        let len = self.ptr.capacity();
+        self.try_iter().take(len)
+    }
+
+    /// Drops all the values available in the queue.
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
 }
 
-impl<T> Clone for Receiver<T> {
+impl<T, A: Allocator + Clone, C: Capacity, L: CellLayout> Clone for Receiver<T, A, C, L> {
     fn clone(&self) -> Self {
+        self.ptr.receivers().fetch_add(1, Ordering::AcqRel);
         Self {
             ptr: self.ptr.clone(),
             local_head: self.ptr.head().load(Ordering::Relaxed),
@@ -77,4 +126,16 @@ impl<T> Clone for Receiver<T> {
     }
 }
 
-unsafe impl<T: Send> Send for Receiver<T> {}
+impl<T, A: Allocator, C: Capacity, L: CellLayout> Drop for Receiver<T, A, C, L> {
+    fn drop(&mut self) {
+        // nobody is going to receive the values sent from now on
+        if self.ptr.receivers().fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.ptr.closed().store(true, Ordering::Release);
+        }
+    }
+}
+
+unsafe impl<T: Send, A: Allocator + Send, C: Capacity, L: CellLayout> Send
+    for Receiver<T, A, C, L>
+{
+}
@@ -1,22 +1,44 @@
-use crate::{atomic::Ordering, spmc::queue::QueuePtr};
+use crate::{
+    Allocator, Capacity, CellLayout, Dynamic, Global, PaddedCells, atomic::Ordering,
+    spmc::queue::QueuePtr,
+};
 
-pub struct Sender<T> {
-    ptr: QueuePtr<T>,
+pub struct Sender<T, A: Allocator = Global, C: Capacity = Dynamic, L: CellLayout = PaddedCells> {
+    ptr: QueuePtr<T, A, C, L>,
     local_tail: usize,
 }
 
-impl<T> Sender<T> {
-    pub(crate) fn new(queue_ptr: QueuePtr<T>) -> Self {
+impl<T, A: Allocator, C: Capacity, L: CellLayout> Sender<T, A, C, L> {
+    pub(crate) fn new(queue_ptr: QueuePtr<T, A, C, L>) -> Self {
+        queue_ptr.senders().fetch_add(1, Ordering::AcqRel);
         Self {
             ptr: queue_ptr,
             local_tail: 0,
         }
     }
 
+    /// Returns `true` if a receiver closed the queue with [`Receiver::close`], or if all the
+    /// receivers were dropped.
+    ///
+    /// Sending fails from then on. This can be used to cancel the work producing the values
+    /// early.
+    ///
+    /// [`Receiver::close`]: super::Receiver::close
+    pub fn is_closed(&self) -> bool {
+        self.ptr.closed().load(Ordering::Acquire)
+    }
+
     pub fn send(&mut self, value: T) {
+        if self.ptr.closed().load(Ordering::Relaxed) {
+            return;
+        }
+
         let cell = self.ptr.at(self.local_tail);
         let mut backoff = crate::Backoff::with_spin_count(128);
         while cell.epoch().load(Ordering::Acquire) != self.local_tail {
+            if self.ptr.closed().load(Ordering::Relaxed) {
+                return;
+            }
             backoff.backoff();
         }
 
@@ -27,6 +49,10 @@ impl<T> Sender<T> {
     }
 
     pub fn try_send(&mut self, value: T) -> Result<(), T> {
+        if self.ptr.closed().load(Ordering::Relaxed) {
+            return Err(value);
+        }
+
         let cell = self.ptr.at(self.local_tail);
         if cell.epoch().load(Ordering::Acquire) != self.local_tail {
             return Err(value);
@@ -41,4 +67,10 @@ impl<T> Sender<T> {
     }
 }
 
-unsafe impl<T: Send> Send for Sender<T> {}
+impl<T, A: Allocator, C: Capacity, L: CellLayout> Drop for Sender<T, A, C, L> {
+    fn drop(&mut self) {
+        self.ptr.senders().fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+unsafe impl<T: Send, A: Allocator + Send, C: Capacity, L: CellLayout> Send for Sender<T, A, C, L> {}
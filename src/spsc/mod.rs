@@ -19,15 +19,34 @@
 //!
 //! * [Facebook Folly ProducerConsumerQueue](https://github.com/facebook/folly/blob/main/folly/ProducerConsumerQueue.h)
 
+#[cfg(feature = "alloc")]
 use core::num::NonZeroUsize;
 
+#[cfg(feature = "alloc")]
+use crate::{Allocator, Const, Dynamic, Global};
+
+#[cfg(feature = "alloc")]
 pub(crate) use self::queue::QueuePtr;
+#[cfg(feature = "alloc")]
 pub(crate) mod shards;
+#[cfg(all(feature = "async", feature = "alloc"))]
+pub use self::receiver::RecvManyFuture;
+#[cfg(not(feature = "loom"))]
+pub use self::static_queue::{Consumer, Producer, StaticQueue};
 pub use self::{receiver::Receiver, sender::Sender};
+#[cfg(feature = "async")]
+pub use self::{
+    receiver::{ReadBufferFuture, ReceiverClosedFuture, RecvFuture},
+    sender::{SendFuture, SenderClosedFuture, WriteBufferFuture},
+};
 
 mod queue;
 mod receiver;
 mod sender;
+#[cfg(all(feature = "shm", unix, not(feature = "loom")))]
+pub mod shm;
+#[cfg(not(feature = "loom"))]
+mod static_queue;
 
 /// Creates a new single-producer single-consumer (SPSC) queue.
 ///
@@ -49,18 +68,214 @@ mod sender;
 ///
 /// let (tx, rx) = channel::<usize>(NonZeroUsize::new(1024).unwrap());
 /// ```
+#[cfg(feature = "alloc")]
 pub fn channel<T>(capacity: NonZeroUsize) -> (Sender<T>, Receiver<T>) {
-    let queue = queue::QueuePtr::with_size(capacity);
+    channel_in(capacity, Global)
+}
+
+/// Creates a new single-producer single-consumer (SPSC) queue in the given allocator.
+///
+/// Same as [`channel`], but the queue is allocated with `alloc` instead of the [`Global`]
+/// allocator. Every handle keeps a clone of `alloc`, the last one dropped frees the queue with it.
+///
+/// # Examples
+///
+/// ```
+/// use core::num::NonZeroUsize;
+/// use gil::{Global, spsc::channel_in};
+///
+/// let (tx, rx) = channel_in::<usize, _>(NonZeroUsize::new(1024).unwrap(), Global);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn channel_in<T, A: Allocator + Clone>(
+    capacity: NonZeroUsize,
+    alloc: A,
+) -> (Sender<T, A>, Receiver<T, A>) {
+    let queue = queue::QueuePtr::with_capacity_in(Dynamic::new(capacity), alloc);
     (Sender::new(queue.clone()), Receiver::new(queue))
 }
 
-#[cfg(all(test, not(feature = "loom")))]
+/// Creates a new single-producer single-consumer (SPSC) queue with a capacity known at compile time.
+///
+/// Same as [`channel`], but the capacity `N` is part of the types of the handles, so the compiler
+/// folds the index masking and the space checks into constants. `N` must be a power of two, which
+/// is checked at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use gil::{Const, Global, spsc::{Receiver, Sender, channel_const}};
+///
+/// let (tx, rx): (Sender<usize, Global, Const<1024>>, Receiver<usize, Global, Const<1024>>) =
+///     channel_const::<usize, 1024>();
+/// ```
+#[cfg(feature = "alloc")]
+pub fn channel_const<T, const N: usize>()
+-> (Sender<T, Global, Const<N>>, Receiver<T, Global, Const<N>>) {
+    let queue = queue::QueuePtr::with_capacity_in(Const::<N>::new(), Global);
+    (Sender::new(queue.clone()), Receiver::new(queue))
+}
+
+#[cfg(all(test, feature = "alloc", not(feature = "loom")))]
 mod test {
     use std::num::NonZeroUsize;
 
     use super::*;
     use crate::thread;
 
+    #[test]
+    fn test_channel_const() {
+        let (mut tx, mut rx) = channel_const::<usize, 4>();
+        for i in 0..4 {
+            tx.try_send(i).unwrap();
+        }
+        assert!(tx.try_send(4).is_err());
+
+        for i in 0..4 {
+            assert_eq!(rx.try_recv(), Some(i));
+        }
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_peek() {
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(4).unwrap());
+        assert_eq!(rx.peek(), None);
+        assert_eq!(rx.recv_if(|_| true), None);
+
+        tx.send(1);
+        tx.send(2);
+        assert_eq!(rx.peek(), Some(&1));
+        assert_eq!(rx.peek(), Some(&1));
+        assert_eq!(rx.recv_if(|&v| v == 2), None);
+        assert_eq!(rx.recv_if(|&v| v == 1), Some(1));
+        assert_eq!(rx.peek(), Some(&2));
+        assert_eq!(rx.try_recv(), Some(2));
+        assert_eq!(rx.peek(), None);
+    }
+
+    #[test]
+    fn test_is_closed() {
+        let (mut tx, rx) = channel::<usize>(NonZeroUsize::new(4).unwrap());
+        assert!(!tx.is_closed() && !rx.is_closed());
+        drop(rx);
+        assert!(tx.is_closed());
+        tx.send(0);
+
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(4).unwrap());
+        tx.send(1);
+        drop(tx);
+        assert!(rx.is_closed());
+        assert!(rx.iter().eq([1]));
+    }
+
+    #[test]
+    fn test_close() {
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(2).unwrap());
+        tx.send(0);
+        tx.send(1);
+        let handle = thread::spawn(move || {
+            // blocked on the full queue until it is closed
+            tx.send(2);
+            assert!(tx.is_closed());
+            assert_eq!(tx.try_send(3), Err(3));
+            assert!(tx.write_buffer().is_empty());
+        });
+
+        rx.close();
+        handle.join().unwrap();
+        assert!(rx.is_closed());
+        assert!(rx.drain().eq(0..2));
+    }
+
+    #[test]
+    fn test_drain() {
+        use std::sync::Arc;
+
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(8).unwrap());
+        for i in 0..5 {
+            tx.send(i);
+        }
+
+        let mut drain = rx.drain();
+        assert_eq!(drain.next(), Some(0));
+        // sent after the drain started, left for the next call
+        tx.send(5);
+        assert!(drain.eq(1..5));
+        assert!(rx.try_iter().eq(5..6));
+
+        let item = Arc::new(());
+        let (mut tx, mut rx) = channel::<Arc<()>>(NonZeroUsize::new(8).unwrap());
+        for _ in 0..5 {
+            tx.send(item.clone());
+        }
+        rx.clear();
+        assert_eq!(Arc::strong_count(&item), 1);
+        assert!(rx.try_recv().is_none());
+
+        tx.send(item.clone());
+        assert_eq!(rx.iter().next().map(|v| Arc::ptr_eq(&v, &item)), Some(true));
+    }
+
+    #[test]
+    fn test_clear_panic() {
+        use std::panic::{AssertUnwindSafe, catch_unwind};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct PanicOnDrop(bool);
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+                if self.0 {
+                    panic!("drop");
+                }
+            }
+        }
+
+        let (mut tx, mut rx) = channel::<PanicOnDrop>(NonZeroUsize::new(8).unwrap());
+        tx.send(PanicOnDrop(false));
+        tx.send(PanicOnDrop(true));
+        tx.send(PanicOnDrop(false));
+
+        assert!(catch_unwind(AssertUnwindSafe(|| rx.clear())).is_err());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+
+        // the values already dropped are gone, the rest is still there
+        let value = rx.try_recv().unwrap();
+        assert!(!value.0);
+        assert!(rx.try_recv().is_none());
+        drop(value);
+        drop((tx, rx));
+        assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_discard_drops() {
+        use std::sync::Arc;
+
+        let item = Arc::new(());
+        let (mut tx, mut rx) = channel::<Arc<()>>(NonZeroUsize::new(8).unwrap());
+        for _ in 0..5 {
+            tx.send(item.clone());
+        }
+
+        assert_eq!(rx.read_buffer().len(), 5);
+        unsafe { rx.discard(3) };
+        assert_eq!(Arc::strong_count(&item), 3);
+
+        // `advance` leaves the items to the caller, who moved them out
+        let moved = unsafe { core::ptr::read(&rx.read_buffer()[0]) };
+        unsafe { rx.advance(1) };
+        assert_eq!(Arc::strong_count(&item), 3);
+        drop(moved);
+
+        drop((tx, rx));
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
     #[test]
     fn test_valid_sends() {
         const COUNTS: NonZeroUsize = NonZeroUsize::new(4096).unwrap();
@@ -68,13 +283,13 @@ mod test {
 
         thread::spawn(move || {
             for i in 0..COUNTS.get() << 3 {
-                tx.send(i as usize);
+                tx.send(i);
             }
         });
 
         for i in 0..COUNTS.get() << 3 {
             let r = rx.recv();
-            assert_eq!(r, i as usize);
+            assert_eq!(r, i);
         }
     }
 
@@ -118,6 +333,300 @@ mod test {
         });
     }
 
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_poll() {
+        use core::{
+            future::Future,
+            pin::Pin,
+            task::{Context, Poll},
+        };
+
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(1).unwrap());
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+        assert_eq!(tx.poll_ready(&mut cx), Poll::Ready(()));
+        tx.start_send(1);
+        assert_eq!(tx.poll_ready(&mut cx), Poll::Pending);
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(1));
+
+        let mut recv: RecvFuture<'_, usize> = rx.recv_async();
+        assert_eq!(Pin::new(&mut recv).poll(&mut cx), Poll::Pending);
+
+        let mut send: SendFuture<'_, usize> = tx.send_async(2);
+        assert_eq!(Pin::new(&mut send).poll(&mut cx), Poll::Ready(()));
+        drop(send);
+        let mut send = tx.send_async(3);
+        assert_eq!(Pin::new(&mut send).poll(&mut cx), Poll::Pending);
+
+        assert_eq!(Pin::new(&mut recv).poll(&mut cx), Poll::Ready(2));
+        assert_eq!(Pin::new(&mut send).poll(&mut cx), Poll::Ready(()));
+        drop((recv, send));
+        assert_eq!(rx.try_recv(), Some(3));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_cancel() {
+        use core::{
+            future::Future,
+            pin::Pin,
+            task::{Context, Poll},
+        };
+
+        const TOTAL: usize = 1 << 14;
+
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(1).unwrap());
+        let mut recv = rx.recv_async();
+        assert_eq!(Pin::new(&mut recv).poll(&mut cx), Poll::Pending);
+        drop(recv);
+        tx.send(0);
+        let mut send = tx.send_async(1);
+        assert_eq!(Pin::new(&mut send).poll(&mut cx), Poll::Pending);
+        assert_eq!(send.cancel(), Some(1));
+        assert_eq!(rx.try_recv(), Some(0));
+        assert_eq!(rx.try_recv(), None);
+
+        // every future is polled once and dropped if it isn't ready, nothing is lost or duplicated
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(4).unwrap());
+        thread::spawn(move || {
+            let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+            let mut value = 0;
+            while value < TOTAL {
+                let mut send = tx.send_async(value);
+                match Pin::new(&mut send).poll(&mut cx) {
+                    Poll::Ready(()) => value += 1,
+                    Poll::Pending => {
+                        assert_eq!(send.cancel(), Some(value));
+                        thread::yield_now();
+                    }
+                }
+            }
+        });
+
+        let mut expected = 0;
+        while expected < TOTAL {
+            match Pin::new(&mut rx.recv_async()).poll(&mut cx) {
+                Poll::Ready(value) => {
+                    assert_eq!(value, expected);
+                    expected += 1;
+                }
+                Poll::Pending => thread::yield_now(),
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_budget() {
+        use core::{
+            num::NonZeroU32,
+            sync::atomic::{AtomicUsize, Ordering},
+            task::{Context, Poll},
+        };
+        use std::sync::Arc;
+
+        use futures::task::ArcWake;
+
+        struct Counter(AtomicUsize);
+
+        impl ArcWake for Counter {
+            fn wake_by_ref(arc_self: &Arc<Self>) {
+                arc_self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let waker = futures::task::waker(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let (mut tx, rx) = channel::<usize>(NonZeroUsize::new(8).unwrap());
+        let mut rx = rx.with_budget(NonZeroU32::new(3).unwrap());
+        for i in 0..8 {
+            tx.send(i);
+        }
+
+        for i in 0..3 {
+            assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(i));
+        }
+        // out of budget, the task is woken up to yield
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+        assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+        for i in 3..6 {
+            assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(i));
+        }
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+        assert_eq!(counter.0.load(Ordering::Relaxed), 2);
+
+        // yielding on an empty queue replenishes the budget
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(6));
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(7));
+        assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+        assert_eq!(counter.0.load(Ordering::Relaxed), 2);
+        for i in 8..11 {
+            tx.send(i);
+        }
+        assert_eq!(counter.0.load(Ordering::Relaxed), 3);
+        for i in 8..11 {
+            assert_eq!(futures::executor::block_on(rx.recv_async()), i);
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_closed() {
+        use core::{
+            future::Future,
+            pin::Pin,
+            task::{Context, Poll},
+        };
+
+        let (mut tx, rx) = channel::<usize>(NonZeroUsize::new(4).unwrap());
+        let handle = thread::spawn(move || drop(rx));
+        futures::executor::block_on(tx.closed());
+        handle.join().unwrap();
+
+        let (tx, mut rx) = channel::<usize>(NonZeroUsize::new(4).unwrap());
+        let handle = thread::spawn(move || drop(tx));
+        futures::executor::block_on(rx.closed());
+        handle.join().unwrap();
+
+        // waiting for the close doesn't get in the way of sending
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(1).unwrap());
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        let mut closed = tx.closed();
+        assert_eq!(Pin::new(&mut closed).poll(&mut cx), Poll::Pending);
+        drop(closed);
+        tx.send(0);
+        assert_eq!(rx.recv(), 0);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_close() {
+        let (mut tx, mut rx) = channel::<usize>(NonZeroUsize::new(1).unwrap());
+        tx.send(0);
+        let handle = thread::spawn(move || {
+            futures::executor::block_on(async {
+                // waiting for room until the queue is closed
+                tx.send_async(1).await;
+                assert!(tx.write_buffer_async().await.is_empty());
+                tx.closed().await;
+            });
+        });
+
+        rx.close();
+        handle.join().unwrap();
+        assert_eq!(rx.try_recv(), Some(0));
+        assert_eq!(rx.try_recv(), None);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_batch() {
+        const COUNTS: NonZeroUsize = NonZeroUsize::new(16).unwrap();
+        const TOTAL: usize = COUNTS.get() << 8;
+
+        let (mut tx, mut rx) = channel::<usize>(COUNTS);
+        thread::spawn(move || {
+            futures::executor::block_on(async {
+                let mut i = 0;
+                while i < TOTAL {
+                    let buffer = tx.write_buffer_async().await;
+                    let len = buffer.len().min(TOTAL - i).min(5);
+                    for slot in &mut buffer[..len] {
+                        slot.write(i);
+                        i += 1;
+                    }
+                    unsafe { tx.commit(len) };
+                }
+            });
+        });
+
+        futures::executor::block_on(async {
+            let mut i = 0;
+            while i < TOTAL / 2 {
+                let buffer = rx.read_buffer_async().await;
+                assert!(!buffer.is_empty());
+                let len = buffer.len();
+                for value in buffer {
+                    assert_eq!(*value, i);
+                    i += 1;
+                }
+                unsafe { rx.advance(len) };
+            }
+
+            let start = i;
+            let mut values = std::vec::Vec::new();
+            assert_eq!(rx.recv_many_async(&mut values, 0).await, 0);
+            while i < TOTAL {
+                let len = rx.recv_many_async(&mut values, 7).await;
+                assert!((1..=7).contains(&len));
+                i += len;
+            }
+            assert!(values.into_iter().eq(start..TOTAL));
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_mixed() {
+        const COUNTS: NonZeroUsize = NonZeroUsize::new(16).unwrap();
+        const TOTAL: usize = COUNTS.get() << 8;
+
+        // sync sender, async receiver
+        let (mut tx, mut rx) = channel::<usize>(COUNTS);
+        thread::spawn(move || {
+            let mut i = 0;
+            while i < TOTAL {
+                if i % 3 == 0 {
+                    let buffer = tx.write_buffer();
+                    if let Some(slot) = buffer.first_mut() {
+                        slot.write(i);
+                        unsafe { tx.commit(1) };
+                        i += 1;
+                    }
+                } else if i % 3 == 1 {
+                    if tx.try_send(i).is_ok() {
+                        i += 1;
+                    }
+                } else {
+                    tx.send(i);
+                    i += 1;
+                }
+            }
+        });
+        futures::executor::block_on(async {
+            for i in 0..TOTAL {
+                assert_eq!(rx.recv_async().await, i);
+            }
+        });
+
+        // async sender, sync receiver
+        let (mut tx, mut rx) = channel::<usize>(COUNTS);
+        thread::spawn(move || {
+            futures::executor::block_on(async {
+                for i in 0..TOTAL {
+                    tx.send_async(i).await;
+                }
+            });
+        });
+        let mut i = 0;
+        while i < TOTAL {
+            if i % 2 == 0 {
+                assert_eq!(rx.recv(), i);
+                i += 1;
+            } else if let Some(&value) = rx.read_buffer().first() {
+                assert_eq!(value, i);
+                unsafe { rx.advance(1) };
+                i += 1;
+            }
+        }
+    }
+
     #[test]
     fn test_batched_send_recv() {
         const CAPACITY: NonZeroUsize = NonZeroUsize::new(1024).unwrap();
@@ -129,8 +638,8 @@ mod test {
             while sent < TOTAL_ITEMS {
                 let buffer = tx.write_buffer();
                 let batch_size = buffer.len().min(TOTAL_ITEMS - sent);
-                for i in 0..batch_size {
-                    buffer[i].write(sent + i);
+                for (i, slot) in buffer.iter_mut().take(batch_size).enumerate() {
+                    slot.write(sent + i);
                 }
                 unsafe { tx.commit(batch_size) };
                 sent += batch_size;
@@ -193,6 +702,71 @@ mod test {
     }
 }
 
+#[cfg(all(test, not(feature = "loom")))]
+mod static_test {
+    use super::*;
+    use crate::thread;
+
+    #[test]
+    fn test_static_queue() {
+        const COUNTS: usize = 4096;
+        let mut queue = StaticQueue::<usize, 64>::new();
+        let (mut tx, mut rx) = queue.split();
+
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                for i in 0..COUNTS {
+                    tx.send(i);
+                }
+            });
+
+            for i in 0..COUNTS {
+                assert_eq!(rx.recv(), i);
+            }
+        });
+
+        // splitting again picks up where the previous handles left
+        let (mut tx, rx) = queue.split();
+        for i in 0..64 {
+            tx.try_send(i).unwrap();
+        }
+        assert!(tx.try_send(64).is_err());
+        drop((tx, rx));
+
+        let (_tx, mut rx) = queue.split();
+        for i in 0..64 {
+            assert_eq!(rx.try_recv(), Some(i));
+        }
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_static_queue_drop() {
+        use std::sync::Arc;
+
+        let item = Arc::new(());
+        {
+            let mut queue = StaticQueue::<Arc<()>, 8>::new();
+            let (mut tx, _rx) = queue.split();
+            for _ in 0..5 {
+                tx.send(item.clone());
+            }
+            assert_eq!(Arc::strong_count(&item), 6);
+        }
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn test_static_in_static() {
+        static mut QUEUE: StaticQueue<u8, 4> = StaticQueue::new();
+
+        let queue = &raw mut QUEUE;
+        let (mut tx, mut rx) = unsafe { (*queue).split() };
+        tx.send(1);
+        assert_eq!(rx.recv(), 1);
+    }
+}
+
 #[cfg(all(test, feature = "loom"))]
 mod loom_test {
     use core::num::NonZeroUsize;
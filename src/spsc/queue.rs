@@ -1,20 +1,22 @@
 #[cfg(feature = "async")]
 use core::task::Waker;
 use core::{
+    alloc::Layout,
     marker::PhantomData,
     mem::{align_of, size_of},
-    num::NonZeroUsize,
     ptr::NonNull,
 };
 
 #[cfg(feature = "async")]
 use futures::task::AtomicWaker;
 
-#[cfg(feature = "async")]
-use crate::atomic::AtomicBool;
+#[cfg(feature = "alloc")]
+use crate::allocator;
+#[cfg(not(feature = "loom"))]
+use crate::allocator::NoAlloc;
 use crate::{
-    alloc,
-    atomic::{AtomicUsize, Ordering},
+    Allocator, Capacity, Dynamic, Global,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
     padded::Padded,
 };
 
@@ -22,7 +24,7 @@ use crate::{
 /// - tail should always point to the place where we can write next to.
 // avoid re-ordering fields
 #[repr(C)]
-struct Queue {
+pub(crate) struct Queue {
     head: Padded<AtomicUsize>,
     #[cfg(feature = "async")]
     sender_sleeping: Padded<AtomicBool>,
@@ -36,91 +38,125 @@ struct Queue {
     sender_waker: Padded<AtomicWaker>,
 
     rc: AtomicUsize,
+    /// The number of senders and receivers using the queue, the other references don't count.
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+    /// Set once the receivers closed the queue, sending fails from then on.
+    closed: AtomicBool,
+}
+
+macro_rules! new_queue {
+    () => {
+        Queue {
+            head: Padded::new(AtomicUsize::new(0)),
+            tail: Padded::new(AtomicUsize::new(0)),
+
+            #[cfg(feature = "async")]
+            sender_sleeping: Padded::new(AtomicBool::new(false)),
+
+            #[cfg(feature = "async")]
+            receiver_sleeping: Padded::new(AtomicBool::new(false)),
+
+            #[cfg(feature = "async")]
+            sender_waker: Padded::new(AtomicWaker::new()),
+
+            #[cfg(feature = "async")]
+            receiver_waker: Padded::new(AtomicWaker::new()),
+
+            rc: AtomicUsize::new(1),
+            senders: AtomicUsize::new(0),
+            receivers: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+        }
+    };
 }
 
-pub(crate) struct QueuePtr<T> {
+impl Queue {
+    /// An empty queue, with a single reference held by its owner.
+    #[cfg(not(feature = "loom"))]
+    pub(crate) const fn new() -> Self {
+        new_queue!()
+    }
+
+    // loom atomics can't be created in const context
+    #[cfg(feature = "loom")]
+    pub(crate) fn new() -> Self {
+        new_queue!()
+    }
+}
+
+pub(crate) struct QueuePtr<T, A: Allocator = Global, C: Capacity = Dynamic> {
     ptr: NonNull<Queue>,
     buffer: NonNull<T>,
-    pub(crate) size: usize,
-    pub(crate) mask: usize,
-    pub(crate) capacity: usize,
+    cap: C,
+    alloc: A,
     _marker: PhantomData<T>,
 }
 
-impl<T> Clone for QueuePtr<T> {
+impl<T, A: Allocator + Clone, C: Capacity> Clone for QueuePtr<T, A, C> {
     fn clone(&self) -> Self {
         let rc = unsafe { _field!(Queue, self.ptr, rc, AtomicUsize).as_ref() };
         rc.fetch_add(1, Ordering::AcqRel);
         Self {
             ptr: self.ptr,
             buffer: self.buffer,
-            size: self.size,
-            mask: self.mask,
-            capacity: self.capacity,
+            cap: self.cap,
+            alloc: self.alloc.clone(),
             _marker: PhantomData,
         }
     }
 }
 
-impl<T> QueuePtr<T> {
-    pub(crate) fn with_size(size: NonZeroUsize) -> Self {
+impl<T, A: Allocator, C: Capacity> QueuePtr<T, A, C> {
+    #[cfg(feature = "alloc")]
+    pub(crate) fn with_capacity_in(cap: C, alloc: A) -> Self {
         // Allocate exactly capacity + 1 slots (one slot is always empty to distinguish full from empty)
-        let size = size.get();
-        let capacity = size.next_power_of_two();
+        let capacity = cap.capacity();
 
         let (layout, buffer_offset) = Self::layout(capacity);
 
-        // SAFETY: capacity > 0, so layout is non-zero too
-        let Some(ptr) = NonNull::new(unsafe { alloc::alloc(layout) }) else {
-            alloc::handle_alloc_error(layout);
-        };
-        let ptr = ptr.cast::<Queue>();
+        let ptr = allocator::allocate(&alloc, layout).cast::<Queue>();
 
         // calculate buffer pointer
-        // SAFETY: `ptr` is already checked by NonNull::new above, so this is guaranteed to be
+        // SAFETY: `ptr` is non-null as returned by the allocator, so this is guaranteed to be
         // valid ptr too
         let buffer =
             unsafe { NonNull::new_unchecked(ptr.as_ptr().byte_add(buffer_offset).cast::<T>()) };
 
-        unsafe {
-            ptr.write(Queue {
-                head: Padded::new(AtomicUsize::new(0)),
-                tail: Padded::new(AtomicUsize::new(0)),
-
-                #[cfg(feature = "async")]
-                sender_sleeping: Padded::new(AtomicBool::new(false)),
-
-                #[cfg(feature = "async")]
-                receiver_sleeping: Padded::new(AtomicBool::new(false)),
-
-                #[cfg(feature = "async")]
-                sender_waker: Padded::new(AtomicWaker::new()),
-
-                #[cfg(feature = "async")]
-                receiver_waker: Padded::new(AtomicWaker::new()),
-
-                rc: AtomicUsize::new(1),
-            });
-        };
+        unsafe { ptr.write(Queue::new()) };
 
         Self {
             ptr,
             buffer,
+            alloc,
             _marker: PhantomData,
-            size,
-            capacity,
-            mask: capacity - 1,
+            cap,
         }
     }
 
-    fn layout(capacity: usize) -> (alloc::Layout, usize) {
+    fn layout(capacity: usize) -> (Layout, usize) {
         let header_layout =
-            alloc::Layout::from_size_align(size_of::<Queue>(), align_of::<Queue>()).unwrap();
-        let buffer_layout = alloc::Layout::array::<T>(capacity).unwrap();
+            Layout::from_size_align(size_of::<Queue>(), align_of::<Queue>()).unwrap();
+        let buffer_layout = Layout::array::<T>(capacity).unwrap();
         let (layout, offset) = header_layout.extend(buffer_layout).unwrap();
         (layout.pad_to_align(), offset)
     }
 
+    #[inline(always)]
+    pub(crate) fn size(&self) -> usize {
+        self.cap.size()
+    }
+
+    #[inline(always)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.cap.capacity()
+    }
+
+    #[inline(always)]
+    pub(crate) fn mask(&self) -> usize {
+        self.cap.mask()
+    }
+
     #[inline(always)]
     pub(crate) fn head(&self) -> &AtomicUsize {
         unsafe { _field!(Queue, self.ptr, head.value, AtomicUsize).as_ref() }
@@ -131,6 +167,29 @@ impl<T> QueuePtr<T> {
         unsafe { _field!(Queue, self.ptr, tail.value, AtomicUsize).as_ref() }
     }
 
+    #[inline(always)]
+    pub(crate) fn senders(&self) -> &AtomicUsize {
+        unsafe { _field!(Queue, self.ptr, senders, AtomicUsize).as_ref() }
+    }
+
+    #[inline(always)]
+    pub(crate) fn receivers(&self) -> &AtomicUsize {
+        unsafe { _field!(Queue, self.ptr, receivers, AtomicUsize).as_ref() }
+    }
+
+    #[inline(always)]
+    pub(crate) fn closed(&self) -> &AtomicBool {
+        unsafe { _field!(Queue, self.ptr, closed, AtomicBool).as_ref() }
+    }
+
+    /// Makes sending fail, waking up an async sender waiting for room.
+    pub(crate) fn close(&self) {
+        self.closed().store(true, Ordering::Release);
+
+        #[cfg(feature = "async")]
+        self.wake_sender();
+    }
+
     #[inline(always)]
     pub(crate) unsafe fn exact_at(&self, index: usize) -> NonNull<T> {
         unsafe { NonNull::new_unchecked(self.buffer.as_ptr().add(index)) }
@@ -138,22 +197,22 @@ impl<T> QueuePtr<T> {
 
     #[inline(always)]
     pub(crate) unsafe fn at(&self, index: usize) -> NonNull<T> {
-        unsafe { self.exact_at(index & self.mask) }
+        unsafe { self.exact_at(index & self.mask()) }
     }
 
     #[inline(always)]
     pub(crate) unsafe fn get(&self, index: usize) -> T {
-        unsafe { self.at(index & self.mask).read() }
+        unsafe { self.at(index & self.mask()).read() }
     }
 
     #[inline(always)]
     pub(crate) unsafe fn set(&self, index: usize, value: T) {
-        unsafe { self.at(index & self.mask).write(value) }
+        unsafe { self.at(index & self.mask()).write(value) }
     }
 }
 
 #[cfg(feature = "async")]
-impl<T> QueuePtr<T> {
+impl<T, A: Allocator, C: Capacity> QueuePtr<T, A, C> {
     #[inline(always)]
     pub(crate) fn register_sender_waker(&self, waker: &Waker) {
         unsafe {
@@ -172,6 +231,30 @@ impl<T> QueuePtr<T> {
         }
     }
 
+    /// Forgets the waker of a sender which stopped waiting, so that it isn't woken up or kept
+    /// alive for nothing.
+    #[inline(always)]
+    pub(crate) fn unregister_sender_waker(&self) {
+        self.sender_sleeping().store(false, Ordering::Relaxed);
+        unsafe {
+            _field!(Queue, self.ptr, sender_waker.value, AtomicWaker)
+                .as_ref()
+                .take();
+        }
+    }
+
+    /// Forgets the waker of a receiver which stopped waiting, so that it isn't woken up or kept
+    /// alive for nothing.
+    #[inline(always)]
+    pub(crate) fn unregister_receiver_waker(&self) {
+        self.receiver_sleeping().store(false, Ordering::Relaxed);
+        unsafe {
+            _field!(Queue, self.ptr, receiver_waker.value, AtomicWaker)
+                .as_ref()
+                .take();
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn wake_sender(&self) {
         unsafe {
@@ -201,28 +284,65 @@ impl<T> QueuePtr<T> {
     }
 }
 
-impl<T> Drop for QueuePtr<T> {
+#[cfg(not(feature = "loom"))]
+impl<T, C: Capacity> QueuePtr<T, NoAlloc, C> {
+    /// Creates a new reference to a queue whose memory isn't owned by the queue itself.
+    ///
+    /// # Safety
+    /// `ptr` and `buffer` of `cap.capacity()` elements must stay valid as long as the returned pointer
+    /// and its clones are alive, and the owner must keep its own reference to the queue.
+    pub(crate) unsafe fn from_static(ptr: NonNull<Queue>, buffer: NonNull<T>, cap: C) -> Self {
+        let rc = unsafe { _field!(Queue, ptr, rc, AtomicUsize).as_ref() };
+        rc.fetch_add(1, Ordering::AcqRel);
+        Self {
+            ptr,
+            buffer,
+            cap,
+            alloc: NoAlloc,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, A: Allocator, C: Capacity> QueuePtr<T, A, C> {
+    /// Drops the items still in the queue.
+    ///
+    /// # Safety
+    /// There must be no sender or receiver using the queue anymore.
+    pub(crate) unsafe fn drop_items(&self) {
+        let head = self.head().load(Ordering::Relaxed);
+        let tail = self.tail().load(Ordering::Relaxed);
+        unsafe { self.drop_range(head, tail) };
+    }
+
+    /// Drops the values from `start` to `end`.
+    ///
+    /// # Safety
+    /// The values must be initialised, and nobody else may read them.
+    pub(crate) unsafe fn drop_range(&self, start: usize, end: usize) {
+        let len = end.wrapping_sub(start);
+
+        if core::mem::needs_drop::<T>() {
+            for i in 0..len {
+                let idx = start.wrapping_add(i);
+                unsafe {
+                    core::ptr::drop_in_place(self.at(idx).as_ptr());
+                }
+            }
+        }
+    }
+}
+
+impl<T, A: Allocator, C: Capacity> Drop for QueuePtr<T, A, C> {
     fn drop(&mut self) {
         let rc = unsafe { _field!(Queue, self.ptr, rc, AtomicUsize).as_ref() };
         if rc.fetch_sub(1, Ordering::AcqRel) == 1 {
-            let (layout, _) = Self::layout(self.capacity);
-
-            let head = self.head().load(Ordering::Relaxed);
-            let tail = self.tail().load(Ordering::Relaxed);
-            let len = tail.wrapping_sub(head);
-
-            if core::mem::needs_drop::<T>() {
-                for i in 0..len {
-                    let idx = head.wrapping_add(i);
-                    unsafe {
-                        core::ptr::drop_in_place(self.at(idx).as_ptr());
-                    }
-                }
-            }
+            let (layout, _) = Self::layout(self.capacity());
 
             unsafe {
+                self.drop_items();
                 self.ptr.drop_in_place();
-                alloc::dealloc(self.ptr.cast().as_ptr(), layout);
+                self.alloc.deallocate(self.ptr.cast(), layout);
             }
         }
     }
@@ -1,31 +1,70 @@
-use crate::{atomic::Ordering, spsc::queue::QueuePtr};
+#[cfg(feature = "async")]
+use core::{
+    future::Future,
+    num::NonZeroU32,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+#[cfg(all(feature = "async", feature = "alloc"))]
+use alloc_crate::vec::Vec;
+
+use crate::{Allocator, Capacity, Dynamic, Global, atomic::Ordering, spsc::queue::QueuePtr};
+#[cfg(feature = "async")]
+use crate::{barrier, budget::Budget};
 
 /// The consumer end of the SPSC queue.
 ///
 /// This struct is `Send` but not `Sync` or `Clone`. It can be moved to another thread, but cannot be shared
 /// across threads.
-pub struct Receiver<T> {
-    ptr: QueuePtr<T>,
+pub struct Receiver<T, A: Allocator = Global, C: Capacity = Dynamic> {
+    ptr: QueuePtr<T, A, C>,
     local_tail: usize,
     local_head: usize,
+    #[cfg(feature = "async")]
+    budget: Budget,
 }
 
-impl<T> Receiver<T> {
-    pub(crate) fn new(queue_ptr: QueuePtr<T>) -> Self {
+impl<T, A: Allocator, C: Capacity> Receiver<T, A, C> {
+    pub(crate) fn new(queue_ptr: QueuePtr<T, A, C>) -> Self {
+        // the queue may have been used by a previous receiver already
+        let local_tail = queue_ptr.tail().load(Ordering::Acquire);
+        let local_head = queue_ptr.head().load(Ordering::Relaxed);
+        queue_ptr.receivers().fetch_add(1, Ordering::AcqRel);
+        // and closed by it
+        queue_ptr.closed().store(false, Ordering::Relaxed);
         Self {
             ptr: queue_ptr,
-            local_tail: 0,
-            local_head: 0,
+            local_tail,
+            local_head,
+            #[cfg(feature = "async")]
+            budget: Budget::unlimited(),
         }
     }
 
-    pub(crate) fn is_empty(&self) -> bool {
-        self.local_head == self.local_tail && {
-            // relaxed load is enough as we are only checking for emptiness hint
-            // to avoid expensive locking in sharded implementation
-            let tail = self.ptr.tail().load(Ordering::Relaxed);
-            self.local_head == tail
-        }
+    /// Limits the number of values the async methods return in a row without yielding.
+    ///
+    /// Receiving in a loop from a busy queue never returns `Poll::Pending`, so the task would
+    /// never yield back to the executor and starve the other tasks on its worker. With a budget,
+    /// after `budget` consecutive ready polls the next one wakes the task up and returns
+    /// `Poll::Pending`, letting the executor run something else first. The budget is replenished
+    /// whenever the task yields, on its own or because the queue is empty.
+    ///
+    /// There is no budget by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::num::{NonZeroU32, NonZeroUsize};
+    /// use gil::spsc::channel;
+    ///
+    /// let (tx, rx) = channel::<usize>(NonZeroUsize::new(1024).unwrap());
+    /// let rx = rx.with_budget(NonZeroU32::new(128).unwrap());
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn with_budget(mut self, budget: NonZeroU32) -> Self {
+        self.budget = Budget::new(budget);
+        self
     }
 
     /// Attempts to receive a value from the queue without blocking.
@@ -52,6 +91,37 @@ impl<T> Receiver<T> {
         Some(ret)
     }
 
+    /// Returns a reference to the next value in the queue without receiving it.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(&value)` if a value is available.
+    /// * `None` if the queue is empty.
+    pub fn peek(&self) -> Option<&T> {
+        if self.local_head == self.local_tail
+            && self.local_head == self.ptr.tail().load(Ordering::Acquire)
+        {
+            return None;
+        }
+
+        // SAFETY: head != tail so the value at head is initialised, and only this receiver can
+        //         take it out of the queue, which needs a mutable borrow
+        Some(unsafe { self.ptr.at(self.local_head).as_ref() })
+    }
+
+    /// Receives the next value in the queue if `f` returns `true` for it, without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(value)` if a value is available and accepted by `f`.
+    /// * `None` if the queue is empty or `f` returned `false`, leaving the value in the queue.
+    pub fn recv_if(&mut self, f: impl FnOnce(&T) -> bool) -> Option<T> {
+        if !f(self.peek()?) {
+            return None;
+        }
+        self.try_recv()
+    }
+
     /// Receives a value from the queue, blocking if necessary.
     ///
     /// This method uses a spin loop to wait for available data in the queue.
@@ -73,33 +143,115 @@ impl<T> Receiver<T> {
         ret
     }
 
-    /// Receives a value from the queue asynchronously.
+    /// Returns an iterator receiving values without blocking, until the queue is empty.
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.try_recv())
+    }
+
+    /// Returns an iterator receiving values, blocking for each one until the sender is dropped and
+    /// the queue is empty.
+    ///
+    /// It also ends once the channel is closed with [`Receiver::close`] and emptied.
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        crate::iter::blocking(self, Self::try_recv, Self::is_closed)
+    }
+
+    /// Returns an iterator receiving the values available in the queue when it is called.
+    ///
+    /// Unlike [`Receiver::try_iter`], it ends even if the senders keep sending. Values it didn't
+    /// get to because it was dropped early stay in the queue.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.load_tail();
+        let len = self.local_tail.wrapping_sub(self.local_head);
+        self.try_iter().take(len)
+    }
+
+    /// Drops all the values available in the queue.
+    pub fn clear(&mut self) {
+        self.load_tail();
+        // SAFETY: the values between head and tail are initialised, and only this receiver takes
+        //         them out of the queue
+        unsafe { self.drop_until(self.local_tail) };
+    }
+
+    /// Closes the queue, so that sending fails from then on, without dropping the values already
+    /// in it.
+    ///
+    /// The values can still be received. A sender waiting for room, blocked or async, is woken up
+    /// and its send fails.
+    pub fn close(&mut self) {
+        self.ptr.close();
+    }
+
+    /// Returns `true` if the sender was dropped, or if the queue was closed with
+    /// [`Receiver::close`].
     ///
-    /// This method yields the current task if the queue is empty.
+    /// The values sent before can still be received.
+    pub fn is_closed(&self) -> bool {
+        self.ptr.senders().load(Ordering::Acquire) == 0 || self.ptr.closed().load(Ordering::Acquire)
+    }
+
+    /// Waits until the sender is dropped, or the queue is closed.
+    ///
+    /// # Cancel safety
+    ///
+    /// The future is cancel safe. The waker it registered is dropped with it.
     #[cfg(feature = "async")]
-    pub async fn recv_async(&mut self) -> T {
-        use core::task::Poll;
+    pub fn closed(&mut self) -> ReceiverClosedFuture<'_, T, A, C> {
+        ReceiverClosedFuture {
+            receiver: self,
+            waiting: false,
+        }
+    }
 
-        if self.local_head == self.local_tail {
-            futures::future::poll_fn(|ctx| {
-                self.load_tail();
-                if self.local_head == self.local_tail {
-                    self.ptr.register_receiver_waker(ctx.waker());
-                    self.ptr.receiver_sleeping().store(true, Ordering::SeqCst);
+    /// Checks whether the sender was dropped or the queue closed, registering the current task to
+    /// be woken up once it is otherwise.
+    #[cfg(feature = "async")]
+    pub fn poll_closed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.is_closed() {
+            return Poll::Ready(());
+        }
 
-                    // prevent lost wake
-                    self.local_tail = self.ptr.tail().load(Ordering::SeqCst);
-                    if self.local_head == self.local_tail {
-                        return Poll::Pending;
-                    }
+        // pairs with the wake in the drop of the sender, after it stopped counting
+        self.ptr.register_receiver_waker(cx.waker());
+        if self.is_closed() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
 
-                    // not sleeping anymore
-                    self.ptr.receiver_sleeping().store(false, Ordering::Relaxed);
-                }
-                Poll::Ready(())
-            })
-            .await;
+    /// Receives a value from the queue asynchronously.
+    ///
+    /// The returned future yields the current task if the queue is empty.
+    ///
+    /// # Cancel safety
+    ///
+    /// The future is cancel safe: a value is only taken out of the queue when the future
+    /// completes, so dropping it, e.g. in a losing branch of `select!`, never loses a value. The
+    /// waker it registered is dropped with it.
+    #[cfg(feature = "async")]
+    pub fn recv_async(&mut self) -> RecvFuture<'_, T, A, C> {
+        RecvFuture {
+            receiver: self,
+            waiting: false,
         }
+    }
+
+    /// Attempts to receive a value from the queue, registering the current task to be woken up
+    /// if it is empty.
+    ///
+    /// # Returns
+    ///
+    /// * `Poll::Ready(value)` if a value is available.
+    /// * `Poll::Pending` if the queue is empty, the task is woken up once a value is sent.
+    ///   Also if the receiver ran out of its [budget](Receiver::with_budget), the task is then
+    ///   woken up right away.
+    ///
+    /// A value is only taken out of the queue when `Poll::Ready` is returned. After
+    /// `Poll::Pending`, the waker stays registered until the next poll, or until a value is sent.
+    #[cfg(feature = "async")]
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<T> {
+        ready!(self.poll_available(cx));
 
         // SAFETY: head != tail which means queue is not empty and head has valid initialised
         //         value
@@ -108,11 +260,93 @@ impl<T> Receiver<T> {
         self.store_head(new_head);
         self.local_head = new_head;
 
-        if self.ptr.sender_sleeping().load(Ordering::SeqCst) {
-            self.ptr.wake_sender();
+        Poll::Ready(ret)
+    }
+
+    /// Returns a slice to the available read buffer in the queue, waiting until it holds at least
+    /// one item.
+    ///
+    /// This is the async version of [`Receiver::read_buffer`], the items still have to be marked
+    /// as consumed with [`Receiver::advance`] or [`Receiver::discard`].
+    ///
+    /// # Cancel safety
+    ///
+    /// The future is cancel safe, it doesn't consume anything.
+    #[cfg(feature = "async")]
+    pub fn read_buffer_async(&mut self) -> ReadBufferFuture<'_, T, A, C> {
+        ReadBufferFuture {
+            receiver: Some(self),
+            waiting: false,
         }
+    }
 
-        ret
+    /// Receives up to `limit` values into `buf`, waiting until at least one is available.
+    ///
+    /// The values are appended to `buf`, and the future resolves to how many were received. It
+    /// resolves to `0` right away if `limit` is `0`.
+    ///
+    /// # Cancel safety
+    ///
+    /// The future is cancel safe: values are only taken out of the queue when it completes.
+    #[cfg(all(feature = "async", feature = "alloc"))]
+    pub fn recv_many_async<'a>(
+        &'a mut self,
+        buf: &'a mut Vec<T>,
+        limit: usize,
+    ) -> RecvManyFuture<'a, T, A, C> {
+        RecvManyFuture {
+            receiver: self,
+            buf,
+            limit,
+            waiting: false,
+        }
+    }
+
+    /// Waits until the queue holds at least one item, spending one unit of budget once it does.
+    #[cfg(feature = "async")]
+    fn poll_available(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        ready!(self.budget.poll_proceed(cx));
+
+        if self.local_head == self.local_tail {
+            self.load_tail();
+            if self.local_head == self.local_tail {
+                self.ptr.register_receiver_waker(cx.waker());
+                self.ptr.receiver_sleeping().store(true, Ordering::Relaxed);
+
+                // prevent lost wake, pairs with the light barrier of the sender between storing
+                // the tail and loading the flag
+                barrier::heavy();
+                self.load_tail();
+                if self.local_head == self.local_tail {
+                    self.budget.reset();
+                    return Poll::Pending;
+                }
+
+                // not sleeping anymore
+                self.ptr.receiver_sleeping().store(false, Ordering::Relaxed);
+            }
+        }
+
+        self.budget.spend();
+        Poll::Ready(())
+    }
+
+    /// Moves up to `limit` of the available values into `buf`, publishing the new head once.
+    #[cfg(all(feature = "async", feature = "alloc"))]
+    fn recv_many(&mut self, buf: &mut Vec<T>, limit: usize) -> usize {
+        let len = self.local_tail.wrapping_sub(self.local_head).min(limit);
+        // no allocation can panic halfway through
+        buf.reserve(len);
+        for i in 0..len {
+            // SAFETY: the values between head and tail are initialised, and the head is only
+            //         moved past them below
+            buf.push(unsafe { self.ptr.get(self.local_head.wrapping_add(i)) });
+        }
+
+        let new_head = self.local_head.wrapping_add(len);
+        self.store_head(new_head);
+        self.local_head = new_head;
+        len
     }
 
     /// Returns a slice to the available read buffer in the queue.
@@ -120,7 +354,9 @@ impl<T> Receiver<T> {
     /// This allows reading multiple items directly from the queue's memory (zero-copy),
     /// bypassing the per-item overhead of `recv`.
     ///
-    /// After reading from the buffer, you must call [`Receiver::advance`] to mark the items as consumed.
+    /// After reading from the buffer, you must call [`Receiver::advance`] to mark the items as
+    /// consumed, or [`Receiver::discard`] to also drop them.
+    /// To move the items out of the queue instead, use [`Receiver::drain`].
     ///
     /// # Returns
     ///
@@ -134,8 +370,8 @@ impl<T> Receiver<T> {
             available = self.local_tail.wrapping_sub(self.local_head);
         }
 
-        let start = self.local_head & self.ptr.mask;
-        let contiguous = self.ptr.capacity - start;
+        let start = self.local_head & self.ptr.mask();
+        let contiguous = self.ptr.capacity() - start;
         let len = available.min(contiguous);
 
         unsafe {
@@ -144,9 +380,11 @@ impl<T> Receiver<T> {
         }
     }
 
-    /// Advances the consumer head by `len` items.
+    /// Advances the consumer head by `len` items, without dropping them.
     ///
-    /// This should be called after processing items obtained via [`Receiver::read_buffer`].
+    /// This should be called after processing items obtained via [`Receiver::read_buffer`]. The
+    /// items are left as they are, which suits `Copy` data or items moved out with
+    /// [`ptr::read`](core::ptr::read). Use [`Receiver::discard`] to drop them instead.
     ///
     /// # Safety
     ///
@@ -156,15 +394,7 @@ impl<T> Receiver<T> {
     #[inline(always)]
     pub unsafe fn advance(&mut self, len: usize) {
         #[cfg(debug_assertions)]
-        {
-            let start = self.local_head & self.ptr.mask;
-            let contiguous = self.ptr.capacity - start;
-            let available = contiguous.min(self.local_tail.wrapping_sub(self.local_head));
-            assert!(
-                len <= available,
-                "advancing ({len}) more than available space ({available})"
-            );
-        }
+        self.assert_readable(len);
 
         // the len can be just right at the edge of buffer, so we need to wrap just in case
         let new_head = self.local_head.wrapping_add(len);
@@ -172,34 +402,236 @@ impl<T> Receiver<T> {
         self.local_head = new_head;
     }
 
+    /// Advances the consumer head by `len` items, dropping them.
+    ///
+    /// Same as [`Receiver::advance`], for items that need to be dropped, like `String` or `Box`.
+    ///
+    /// # Safety
+    ///
+    /// * `len` must be less than or equal to the length of the slice returned by the most recent
+    ///   call to [`Receiver::read_buffer`].
+    /// * None of these items may have been moved out of the slice, e.g. with
+    ///   [`ptr::read`](core::ptr::read).
+    pub unsafe fn discard(&mut self, len: usize) {
+        #[cfg(debug_assertions)]
+        self.assert_readable(len);
+
+        let new_head = self.local_head.wrapping_add(len);
+        // SAFETY: the items are initialised, and the slice borrowing them is gone
+        unsafe { self.drop_until(new_head) };
+    }
+
+    #[cfg(debug_assertions)]
+    fn assert_readable(&self, len: usize) {
+        let start = self.local_head & self.ptr.mask();
+        let contiguous = self.ptr.capacity() - start;
+        let available = contiguous.min(self.local_tail.wrapping_sub(self.local_head));
+        assert!(
+            len <= available,
+            "advancing ({len}) more than available space ({available})"
+        );
+    }
+
     #[inline(always)]
     fn store_head(&self, value: usize) {
         self.ptr.head().store(value, Ordering::Release);
+
+        // every operation wakes an async sender, whichever side is sync
+        #[cfg(feature = "async")]
+        {
+            // pairs with the heavy barrier of the sender between setting the flag and loading the
+            // head again
+            barrier::light();
+            let sleeping = self.ptr.sender_sleeping();
+            if sleeping.load(Ordering::Relaxed) && sleeping.swap(false, Ordering::AcqRel) {
+                self.ptr.wake_sender();
+            }
+        }
+    }
+
+    /// Drops the values up to `new_head` and moves the head past them.
+    ///
+    /// If dropping a value panics, the head is still moved past it, so that the values already
+    /// dropped are never read again. The ones after it stay in the queue.
+    ///
+    /// # Safety
+    /// The values up to `new_head` must be initialised, and not moved out of the queue.
+    unsafe fn drop_until(&mut self, new_head: usize) {
+        struct Guard<'a, T, A: Allocator, C: Capacity> {
+            receiver: &'a mut Receiver<T, A, C>,
+            head: usize,
+        }
+
+        impl<T, A: Allocator, C: Capacity> Drop for Guard<'_, T, A, C> {
+            fn drop(&mut self) {
+                self.receiver.store_head(self.head);
+                self.receiver.local_head = self.head;
+            }
+        }
+
+        let mut guard = Guard {
+            head: self.local_head,
+            receiver: self,
+        };
+        if !core::mem::needs_drop::<T>() {
+            guard.head = new_head;
+            return;
+        }
+        while guard.head != new_head {
+            let value = unsafe { guard.receiver.ptr.at(guard.head) };
+            guard.head = guard.head.wrapping_add(1);
+            unsafe { value.drop_in_place() };
+        }
     }
 
     #[inline(always)]
     fn load_tail(&mut self) {
         self.local_tail = self.ptr.tail().load(Ordering::Acquire);
     }
+}
 
-    #[inline(always)]
-    pub(crate) fn refresh_head(&mut self) {
-        self.local_head = self.ptr.head().load(Ordering::Acquire);
-        if self.local_tail < self.local_head {
-            self.local_tail = self.ptr.tail().load(Ordering::Acquire);
+impl<T, A: Allocator, C: Capacity> Drop for Receiver<T, A, C> {
+    fn drop(&mut self) {
+        // nobody is going to receive the values sent from now on
+        if self.ptr.receivers().fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.ptr.close();
         }
     }
+}
 
-    /// # Safety
-    /// Caller needs to ensure that only one receiver ever access the the `self.ptr` at any time.
-    #[inline(always)]
-    pub(crate) unsafe fn clone_via_ptr(&self) -> Self {
-        Self {
-            ptr: self.ptr.clone(),
-            local_tail: self.local_tail,
-            local_head: self.local_head,
+unsafe impl<T: Send, A: Allocator + Send, C: Capacity> Send for Receiver<T, A, C> {}
+
+/// The future returned by [`Receiver::recv_async`].
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct RecvFuture<'a, T, A: Allocator = Global, C: Capacity = Dynamic> {
+    receiver: &'a mut Receiver<T, A, C>,
+    /// Whether the last poll registered the waker.
+    waiting: bool,
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> Future for RecvFuture<'_, T, A, C> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let this = self.get_mut();
+        let ret = this.receiver.poll_recv(cx);
+        this.waiting = ret.is_pending();
+        ret
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> Drop for RecvFuture<'_, T, A, C> {
+    fn drop(&mut self) {
+        if self.waiting {
+            self.receiver.ptr.unregister_receiver_waker();
+        }
+    }
+}
+
+/// The future returned by [`Receiver::read_buffer_async`].
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct ReadBufferFuture<'a, T, A: Allocator = Global, C: Capacity = Dynamic> {
+    /// Taken out when the future completes, the slice borrows it for `'a`.
+    receiver: Option<&'a mut Receiver<T, A, C>>,
+    /// Whether the last poll registered the waker.
+    waiting: bool,
+}
+
+#[cfg(feature = "async")]
+impl<'a, T, A: Allocator, C: Capacity> Future for ReadBufferFuture<'a, T, A, C> {
+    type Output = &'a [T];
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<&'a [T]> {
+        let this = self.get_mut();
+        let receiver = this.receiver.as_mut().expect("polled after completion");
+        this.waiting = true;
+        ready!(receiver.poll_available(cx));
+        this.waiting = false;
+
+        let receiver = this.receiver.take().unwrap();
+        Poll::Ready(receiver.read_buffer())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> Drop for ReadBufferFuture<'_, T, A, C> {
+    fn drop(&mut self) {
+        if self.waiting
+            && let Some(receiver) = &self.receiver
+        {
+            receiver.ptr.unregister_receiver_waker();
+        }
+    }
+}
+
+/// The future returned by [`Receiver::recv_many_async`].
+#[cfg(all(feature = "async", feature = "alloc"))]
+#[must_use = "futures do nothing unless polled"]
+pub struct RecvManyFuture<'a, T, A: Allocator = Global, C: Capacity = Dynamic> {
+    receiver: &'a mut Receiver<T, A, C>,
+    buf: &'a mut Vec<T>,
+    limit: usize,
+    /// Whether the last poll registered the waker.
+    waiting: bool,
+}
+
+#[cfg(all(feature = "async", feature = "alloc"))]
+impl<T, A: Allocator, C: Capacity> Future for RecvManyFuture<'_, T, A, C> {
+    type Output = usize;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<usize> {
+        let this = self.get_mut();
+        if this.limit == 0 {
+            return Poll::Ready(0);
+        }
+
+        this.waiting = true;
+        ready!(this.receiver.poll_available(cx));
+        this.waiting = false;
+
+        Poll::Ready(this.receiver.recv_many(this.buf, this.limit))
+    }
+}
+
+#[cfg(all(feature = "async", feature = "alloc"))]
+impl<T, A: Allocator, C: Capacity> Drop for RecvManyFuture<'_, T, A, C> {
+    fn drop(&mut self) {
+        if self.waiting {
+            self.receiver.ptr.unregister_receiver_waker();
         }
     }
 }
 
-unsafe impl<T: Send> Send for Receiver<T> {}
+/// The future returned by [`Receiver::closed`].
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct ReceiverClosedFuture<'a, T, A: Allocator = Global, C: Capacity = Dynamic> {
+    receiver: &'a mut Receiver<T, A, C>,
+    /// Whether the last poll registered the waker.
+    waiting: bool,
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> Future for ReceiverClosedFuture<'_, T, A, C> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        let ret = this.receiver.poll_closed(cx);
+        this.waiting = ret.is_pending();
+        ret
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> Drop for ReceiverClosedFuture<'_, T, A, C> {
+    fn drop(&mut self) {
+        if self.waiting {
+            self.receiver.ptr.unregister_receiver_waker();
+        }
+    }
+}
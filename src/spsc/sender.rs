@@ -1,23 +1,35 @@
 use core::mem::MaybeUninit;
+#[cfg(feature = "async")]
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
 
-use crate::{atomic::Ordering, spsc::queue::QueuePtr};
+#[cfg(feature = "async")]
+use crate::barrier;
+use crate::{Allocator, Capacity, Dynamic, Global, atomic::Ordering, spsc::queue::QueuePtr};
 
 /// The producer end of the SPSC queue.
 ///
 /// This struct is `Send` but not `Sync` or `Clone`. It can be moved to another thread, but cannot be shared
 /// across threads.
-pub struct Sender<T> {
-    ptr: QueuePtr<T>,
+pub struct Sender<T, A: Allocator = Global, C: Capacity = Dynamic> {
+    ptr: QueuePtr<T, A, C>,
     local_head: usize,
     local_tail: usize,
 }
 
-impl<T> Sender<T> {
-    pub(crate) fn new(queue_ptr: QueuePtr<T>) -> Self {
+impl<T, A: Allocator, C: Capacity> Sender<T, A, C> {
+    pub(crate) fn new(queue_ptr: QueuePtr<T, A, C>) -> Self {
+        // the queue may have been used by a previous sender already
+        let local_head = queue_ptr.head().load(Ordering::Acquire);
+        let local_tail = queue_ptr.tail().load(Ordering::Relaxed);
+        queue_ptr.senders().fetch_add(1, Ordering::AcqRel);
         Self {
             ptr: queue_ptr,
-            local_head: 0,
-            local_tail: 0,
+            local_head,
+            local_tail,
         }
     }
 
@@ -25,9 +37,13 @@ impl<T> Sender<T> {
     ///
     /// # Returns
     ///
-    /// * `true` if the value was successfully sent.
-    /// * `false` if the queue is full.
+    /// * `Ok(())` if the value was successfully sent.
+    /// * `Err(value)` if the queue is full or closed, returning the original value.
     pub fn try_send(&mut self, value: T) -> Result<(), T> {
+        if self.is_closed_relaxed() {
+            return Err(value);
+        }
+
         let new_tail = self.local_tail.wrapping_add(1);
 
         if new_tail > self.max_tail() {
@@ -48,15 +64,25 @@ impl<T> Sender<T> {
     ///
     /// This method uses a spin loop to wait for available space in the queue.
     /// For a non-blocking alternative, use [`Sender::try_send`].
+    ///
+    /// If the queue is closed, before or while waiting, the value is dropped and this returns
+    /// right away. Use [`Sender::try_send`] to get it back instead.
     pub fn send(&mut self, value: T) {
         let new_tail = self.local_tail.wrapping_add(1);
 
         let mut backoff = crate::Backoff::with_spin_count(128);
         while new_tail > self.max_tail() {
+            if self.is_closed_relaxed() {
+                return;
+            }
             backoff.backoff();
             self.load_head();
         }
 
+        if self.is_closed_relaxed() {
+            return;
+        }
+
         unsafe { self.ptr.set(self.local_tail, value) };
         self.store_tail(new_tail);
         self.local_tail = new_tail;
@@ -64,40 +90,142 @@ impl<T> Sender<T> {
 
     /// Sends a value into the queue asynchronously.
     ///
-    /// This method yields the current task if the queue is full.
+    /// The returned future yields the current task if the queue is full. If the queue is
+    /// closed, it completes right away, dropping the value.
+    ///
+    /// # Cancel safety
+    ///
+    /// The future is cancel safe: the value is only moved into the queue when the future
+    /// completes, so dropping it, e.g. in a losing branch of `select!`, never sends it. The value
+    /// is dropped with the future, unless it is taken back with [`SendFuture::cancel`]. The waker
+    /// it registered is dropped with it.
+    #[cfg(feature = "async")]
+    pub fn send_async(&mut self, value: T) -> SendFuture<'_, T, A, C> {
+        SendFuture {
+            sender: self,
+            value: Some(value),
+            waiting: false,
+        }
+    }
+
+    /// Checks whether there is space for a value in the queue, registering the current task to be
+    /// woken up if it is full.
+    ///
+    /// # Returns
+    ///
+    /// * `Poll::Ready(())` if a value can be sent with [`Sender::start_send`], or if the queue is
+    ///   closed, in which case the value is dropped.
+    /// * `Poll::Pending` if the queue is full, the task is woken up once a value is received or
+    ///   the queue is closed.
+    ///
+    /// After `Poll::Pending`, the waker stays registered until the next poll, or until a value is
+    /// received.
     #[cfg(feature = "async")]
-    pub async fn send_async(&mut self, value: T) {
-        use core::task::Poll;
+    pub fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.is_closed_relaxed() {
+            return Poll::Ready(());
+        }
 
         let new_tail = self.local_tail.wrapping_add(1);
 
         if new_tail > self.max_tail() {
-            futures::future::poll_fn(|ctx| {
+            self.load_head();
+            if new_tail > self.max_tail() {
+                self.ptr.register_sender_waker(cx.waker());
+                self.ptr.sender_sleeping().store(true, Ordering::Relaxed);
+
+                // prevent lost wake, pairs with the light barrier of the receiver between storing
+                // the head and loading the flag
+                barrier::heavy();
                 self.load_head();
-                if new_tail > self.max_tail() {
-                    self.ptr.register_sender_waker(ctx.waker());
-                    self.ptr.sender_sleeping().store(true, Ordering::SeqCst);
-
-                    // prevent lost wake
-                    self.local_head = self.ptr.head().load(Ordering::SeqCst);
-                    if new_tail > self.max_tail() {
-                        return Poll::Pending;
-                    }
-
-                    // not sleeping anymore
-                    self.ptr.sender_sleeping().store(false, Ordering::Relaxed);
+                // the wake of a receiver closing the queue comes after setting the flag
+                if new_tail > self.max_tail() && !self.is_closed_relaxed() {
+                    return Poll::Pending;
                 }
-                Poll::Ready(())
-            })
-            .await;
+
+                // not sleeping anymore
+                self.ptr.sender_sleeping().store(false, Ordering::Relaxed);
+            }
+        }
+
+        Poll::Ready(())
+    }
+
+    /// Sends a value into the space found by [`Sender::poll_ready`].
+    ///
+    /// If the queue is closed, the value is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the queue is full, i.e. the last call to [`Sender::poll_ready`] didn't return
+    /// `Poll::Ready` or a value was sent since.
+    #[cfg(feature = "async")]
+    pub fn start_send(&mut self, value: T) {
+        if self.is_closed_relaxed() {
+            return;
         }
 
+        let new_tail = self.local_tail.wrapping_add(1);
+        assert!(new_tail <= self.max_tail(), "the queue is full");
+
         unsafe { self.ptr.set(self.local_tail, value) };
         self.store_tail(new_tail);
         self.local_tail = new_tail;
+    }
+
+    /// Returns `true` if the receiver closed the queue with [`Receiver::close`] or was dropped.
+    ///
+    /// Sending fails from then on. This can be used to cancel the work producing the values
+    /// early.
+    ///
+    /// [`Receiver::close`]: super::Receiver::close
+    pub fn is_closed(&self) -> bool {
+        self.ptr.closed().load(Ordering::Acquire)
+    }
+
+    /// Waits until the receiver closes the queue or is dropped.
+    ///
+    /// # Cancel safety
+    ///
+    /// The future is cancel safe. The waker it registered is dropped with it.
+    #[cfg(feature = "async")]
+    pub fn closed(&mut self) -> SenderClosedFuture<'_, T, A, C> {
+        SenderClosedFuture {
+            sender: self,
+            waiting: false,
+        }
+    }
+
+    /// Checks whether the queue is closed, registering the current task to be woken up once it
+    /// is otherwise.
+    #[cfg(feature = "async")]
+    pub fn poll_closed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.is_closed() {
+            return Poll::Ready(());
+        }
 
-        if self.ptr.receiver_sleeping().load(Ordering::SeqCst) {
-            self.ptr.wake_receiver();
+        // pairs with the wake of the receiver closing the queue
+        self.ptr.register_sender_waker(cx.waker());
+        if self.is_closed() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+
+    /// Returns a mutable slice to the available write buffer in the queue, waiting until it has
+    /// room for at least one item, or until the queue is closed, in which case it is empty.
+    ///
+    /// This is the async version of [`Sender::write_buffer`], the items still have to be made
+    /// visible with [`Sender::commit`].
+    ///
+    /// # Cancel safety
+    ///
+    /// The future is cancel safe, it doesn't send anything.
+    #[cfg(feature = "async")]
+    pub fn write_buffer_async(&mut self) -> WriteBufferFuture<'_, T, A, C> {
+        WriteBufferFuture {
+            sender: Some(self),
+            waiting: false,
         }
     }
 
@@ -112,7 +240,8 @@ impl<T> Sender<T> {
     /// # Returns
     ///
     /// A mutable slice representing the contiguous free space starting from the current tail.
-    /// Note that this might not represent *all* free space if the buffer wraps around.
+    /// Note that this might not represent *all* free space if the buffer wraps around. It is
+    /// empty if the queue is closed.
     ///
     /// # Usage
     ///
@@ -120,15 +249,19 @@ impl<T> Sender<T> {
     /// [`copy_nonoverlapping`](core::ptr::copy_nonoverlapping) if you want fast copying between
     /// this and your own data.
     pub fn write_buffer(&mut self) -> &mut [MaybeUninit<T>] {
-        let mut available = self.ptr.size - self.local_tail.wrapping_sub(self.local_head);
+        if self.is_closed_relaxed() {
+            return &mut [];
+        }
+
+        let mut available = self.ptr.size() - self.local_tail.wrapping_sub(self.local_head);
 
         if available == 0 {
             self.load_head();
-            available = self.ptr.size - self.local_tail.wrapping_sub(self.local_head);
+            available = self.ptr.size() - self.local_tail.wrapping_sub(self.local_head);
         }
 
-        let start = self.local_tail & self.ptr.mask;
-        let contiguous = self.ptr.capacity - start;
+        let start = self.local_tail & self.ptr.mask();
+        let contiguous = self.ptr.capacity() - start;
         let len = available.min(contiguous);
 
         unsafe {
@@ -148,10 +281,10 @@ impl<T> Sender<T> {
     pub unsafe fn commit(&mut self, len: usize) {
         #[cfg(debug_assertions)]
         {
-            let start = self.local_tail & self.ptr.mask;
-            let contiguous = self.ptr.capacity - start;
+            let start = self.local_tail & self.ptr.mask();
+            let contiguous = self.ptr.capacity() - start;
             let available =
-                contiguous.min(self.ptr.size - self.local_tail.wrapping_sub(self.local_head));
+                contiguous.min(self.ptr.size() - self.local_tail.wrapping_sub(self.local_head));
             assert!(
                 len <= available,
                 "advancing ({len}) more than available space ({available})"
@@ -164,14 +297,32 @@ impl<T> Sender<T> {
         self.local_tail = new_tail;
     }
 
+    /// Whether the queue is closed, no other memory is read depending on it.
+    #[inline(always)]
+    fn is_closed_relaxed(&self) -> bool {
+        self.ptr.closed().load(Ordering::Relaxed)
+    }
+
     #[inline(always)]
     fn max_tail(&self) -> usize {
-        self.local_head.wrapping_add(self.ptr.size)
+        self.local_head.wrapping_add(self.ptr.size())
     }
 
     #[inline(always)]
     fn store_tail(&self, value: usize) {
         self.ptr.tail().store(value, Ordering::Release);
+
+        // every operation wakes an async receiver, whichever side is sync
+        #[cfg(feature = "async")]
+        {
+            // pairs with the heavy barrier of the receiver between setting the flag and loading the
+            // tail again
+            barrier::light();
+            let sleeping = self.ptr.receiver_sleeping();
+            if sleeping.load(Ordering::Relaxed) && sleeping.swap(false, Ordering::AcqRel) {
+                self.ptr.wake_receiver();
+            }
+        }
     }
 
     #[inline(always)]
@@ -180,4 +331,128 @@ impl<T> Sender<T> {
     }
 }
 
-unsafe impl<T: Send> Send for Sender<T> {}
+impl<T, A: Allocator, C: Capacity> Drop for Sender<T, A, C> {
+    fn drop(&mut self) {
+        self.ptr.senders().fetch_sub(1, Ordering::AcqRel);
+
+        // an async receiver may be waiting for the senders to be gone
+        #[cfg(feature = "async")]
+        self.ptr.wake_receiver();
+    }
+}
+
+unsafe impl<T: Send, A: Allocator + Send, C: Capacity> Send for Sender<T, A, C> {}
+
+/// The future returned by [`Sender::send_async`].
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct SendFuture<'a, T, A: Allocator = Global, C: Capacity = Dynamic> {
+    sender: &'a mut Sender<T, A, C>,
+    value: Option<T>,
+    /// Whether the last poll registered the waker.
+    waiting: bool,
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> SendFuture<'_, T, A, C> {
+    /// Cancels the send, returning the value if it wasn't sent yet.
+    pub fn cancel(mut self) -> Option<T> {
+        self.value.take()
+    }
+}
+
+// the value is never pinned, it is moved into the queue
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> Unpin for SendFuture<'_, T, A, C> {}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> Future for SendFuture<'_, T, A, C> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        this.waiting = true;
+        ready!(this.sender.poll_ready(cx));
+        this.waiting = false;
+
+        let value = this.value.take().expect("polled after completion");
+        this.sender.start_send(value);
+        Poll::Ready(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> Drop for SendFuture<'_, T, A, C> {
+    fn drop(&mut self) {
+        if self.waiting {
+            self.sender.ptr.unregister_sender_waker();
+        }
+    }
+}
+
+/// The future returned by [`Sender::write_buffer_async`].
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct WriteBufferFuture<'a, T, A: Allocator = Global, C: Capacity = Dynamic> {
+    /// Taken out when the future completes, the slice borrows it for `'a`.
+    sender: Option<&'a mut Sender<T, A, C>>,
+    /// Whether the last poll registered the waker.
+    waiting: bool,
+}
+
+#[cfg(feature = "async")]
+impl<'a, T, A: Allocator, C: Capacity> Future for WriteBufferFuture<'a, T, A, C> {
+    type Output = &'a mut [MaybeUninit<T>];
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<&'a mut [MaybeUninit<T>]> {
+        let this = self.get_mut();
+        let sender = this.sender.as_mut().expect("polled after completion");
+        this.waiting = true;
+        ready!(sender.poll_ready(cx));
+        this.waiting = false;
+
+        let sender = this.sender.take().unwrap();
+        Poll::Ready(sender.write_buffer())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> Drop for WriteBufferFuture<'_, T, A, C> {
+    fn drop(&mut self) {
+        if self.waiting
+            && let Some(sender) = &self.sender
+        {
+            sender.ptr.unregister_sender_waker();
+        }
+    }
+}
+
+/// The future returned by [`Sender::closed`].
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct SenderClosedFuture<'a, T, A: Allocator = Global, C: Capacity = Dynamic> {
+    sender: &'a mut Sender<T, A, C>,
+    /// Whether the last poll registered the waker.
+    waiting: bool,
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> Future for SenderClosedFuture<'_, T, A, C> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        let ret = this.sender.poll_closed(cx);
+        this.waiting = ret.is_pending();
+        ret
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T, A: Allocator, C: Capacity> Drop for SenderClosedFuture<'_, T, A, C> {
+    fn drop(&mut self) {
+        if self.waiting {
+            self.sender.ptr.unregister_sender_waker();
+        }
+    }
+}
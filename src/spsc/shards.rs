@@ -1,96 +1,126 @@
 use core::{num::NonZeroUsize, ptr::NonNull};
 
 use crate::{
-    alloc,
+    Allocator, Dynamic, Global, alloc, allocator,
     atomic::{AtomicUsize, Ordering},
     padded::Padded,
     spsc,
 };
 
 #[repr(C)]
-pub(crate) struct Shards<T> {
+pub(crate) struct Shards<T, A: Allocator = Global> {
     rc: Padded<AtomicUsize>,
-    queue_ptrs: spsc::QueuePtr<T>,
+    /// The number of shards handed out to senders so far.
+    claimed: AtomicUsize,
+    /// The number of senders alive, a sender handle only counts while bound to a shard.
+    senders: AtomicUsize,
+    queue_ptrs: spsc::QueuePtr<T, A>,
 }
 
-impl<T> Shards<T> {
-    pub fn new(max_shards: NonZeroUsize) -> NonNull<Self> {
+impl<T, A: Allocator> Shards<T, A> {
+    pub fn new(max_shards: NonZeroUsize, alloc: &A) -> NonNull<Self> {
         let layout = Self::layout(max_shards.get());
-        let Some(ptr) = NonNull::new(unsafe { alloc::alloc(layout) }) else {
-            alloc::handle_alloc_error(layout)
-        };
+        let ptr = allocator::allocate(alloc, layout);
 
-        unsafe { ptr.cast::<AtomicUsize>().write(AtomicUsize::new(1)) };
+        let ptr = ptr.cast::<Self>();
+        unsafe {
+            _field!(Shards<T, A>, ptr, rc, AtomicUsize).write(AtomicUsize::new(1));
+            _field!(Shards<T, A>, ptr, claimed, AtomicUsize).write(AtomicUsize::new(0));
+            _field!(Shards<T, A>, ptr, senders, AtomicUsize).write(AtomicUsize::new(0));
+        }
 
-        ptr.cast()
+        ptr
     }
 
     fn layout(max_shards: usize) -> alloc::Layout {
-        let (layout, _offset) = alloc::Layout::new::<Padded<AtomicUsize>>()
-            .extend(alloc::Layout::array::<spsc::QueuePtr<T>>(max_shards).unwrap())
+        // the header without `queue_ptrs`, which is the start of the array
+        let header = alloc::Layout::from_size_align(
+            core::mem::offset_of!(Shards<T, A>, queue_ptrs),
+            core::mem::align_of::<Shards<T, A>>(),
+        )
+        .unwrap();
+        let (layout, _offset) = header
+            .extend(alloc::Layout::array::<spsc::QueuePtr<T, A>>(max_shards).unwrap())
             .unwrap();
 
         layout.pad_to_align()
     }
 
     #[inline(always)]
-    pub(crate) fn at(ptr: NonNull<Self>, shard: usize) -> NonNull<spsc::QueuePtr<T>> {
-        unsafe { _field!(Shards<T>, ptr, queue_ptrs).cast().add(shard) }
+    pub(crate) fn at(ptr: NonNull<Self>, shard: usize) -> NonNull<spsc::QueuePtr<T, A>> {
+        unsafe { _field!(Shards<T, A>, ptr, queue_ptrs).cast().add(shard) }
     }
 }
 
-pub(crate) struct ShardsPtr<T> {
-    ptr: NonNull<Shards<T>>,
+pub(crate) struct ShardsPtr<T, A: Allocator = Global> {
+    ptr: NonNull<Shards<T, A>>,
     max_shards: usize,
+    alloc: A,
 }
 
-impl<T> Clone for ShardsPtr<T> {
+impl<T, A: Allocator + Clone> Clone for ShardsPtr<T, A> {
     fn clone(&self) -> Self {
         self.rc().fetch_add(1, Ordering::AcqRel);
 
         Self {
             ptr: self.ptr,
             max_shards: self.max_shards,
+            alloc: self.alloc.clone(),
         }
     }
 }
 
-impl<T> ShardsPtr<T> {
-    pub fn new(max_shards: NonZeroUsize, capacity_per_shard: NonZeroUsize) -> Self {
-        let ptr = Shards::new(max_shards);
+impl<T, A: Allocator + Clone> ShardsPtr<T, A> {
+    pub fn new(max_shards: NonZeroUsize, capacity_per_shard: NonZeroUsize, alloc: A) -> Self {
+        let ptr = Shards::new(max_shards, &alloc);
 
         for i in 0..max_shards.get() {
             let ptr = Shards::at(ptr, i);
-            unsafe { ptr.write(spsc::QueuePtr::with_size(capacity_per_shard)) };
+            let queue_ptr =
+                spsc::QueuePtr::with_capacity_in(Dynamic::new(capacity_per_shard), alloc.clone());
+            unsafe { ptr.write(queue_ptr) };
         }
 
         Self {
             ptr,
             max_shards: max_shards.get(),
+            alloc,
         }
     }
 
-    pub(crate) fn clone_queue_ptr(&self, shard: usize) -> spsc::QueuePtr<T> {
+    pub(crate) fn clone_queue_ptr(&self, shard: usize) -> spsc::QueuePtr<T, A> {
         unsafe { Shards::at(self.ptr, shard).as_ref() }.clone()
     }
+}
 
+impl<T, A: Allocator> ShardsPtr<T, A> {
     fn rc(&self) -> &AtomicUsize {
-        unsafe { _field!(Shards<T>, self.ptr, rc, AtomicUsize).as_ref() }
+        unsafe { _field!(Shards<T, A>, self.ptr, rc, AtomicUsize).as_ref() }
+    }
+
+    #[inline(always)]
+    pub(crate) fn claimed(&self) -> &AtomicUsize {
+        unsafe { _field!(Shards<T, A>, self.ptr, claimed, AtomicUsize).as_ref() }
+    }
+
+    #[inline(always)]
+    pub(crate) fn senders(&self) -> &AtomicUsize {
+        unsafe { _field!(Shards<T, A>, self.ptr, senders, AtomicUsize).as_ref() }
     }
 }
 
-impl<T> Drop for ShardsPtr<T> {
+impl<T, A: Allocator> Drop for ShardsPtr<T, A> {
     fn drop(&mut self) {
         if self.rc().fetch_sub(1, Ordering::AcqRel) == 1 {
             unsafe {
-                _field!(Shards<T>, self.ptr, rc, AtomicUsize).drop_in_place();
+                _field!(Shards<T, A>, self.ptr, rc, AtomicUsize).drop_in_place();
+                _field!(Shards<T, A>, self.ptr, claimed, AtomicUsize).drop_in_place();
+                _field!(Shards<T, A>, self.ptr, senders, AtomicUsize).drop_in_place();
                 for i in 0..self.max_shards {
                     Shards::at(self.ptr, i).drop_in_place();
                 }
-                alloc::dealloc(
-                    self.ptr.as_ptr().cast(),
-                    Shards::<T>::layout(self.max_shards),
-                );
+                self.alloc
+                    .deallocate(self.ptr.cast(), Shards::<T, A>::layout(self.max_shards));
             }
         }
     }
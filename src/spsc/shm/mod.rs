@@ -0,0 +1,338 @@
+//! Inter-process SPSC queue over shared memory.
+//!
+//! [`create`] places the queue header and buffer in a file-backed shared mapping, typically in
+//! `/dev/shm`, and [`open`] maps that same file in another process after checking that its layout
+//! matches the one expected for `T`. Each side then turns its [`Segment`] into a [`Sender`] or a
+//! [`Receiver`], which use the same head/tail algorithm as the in-process
+//! [`spsc`](crate::spsc) queue.
+//!
+//! # Safety
+//!
+//! `T` is copied between processes as raw bytes, so it must not contain pointers, references or
+//! anything else that is only meaningful inside one process. Both processes must also agree on the
+//! type: only its size and alignment can be checked when opening.
+//!
+//! There must be at most one [`Sender`] and one [`Receiver`] for a segment across all processes.
+//! None of this can be checked, so [`open`], [`Segment::into_sender`] and
+//! [`Segment::into_receiver`] are `unsafe`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use core::num::NonZeroUsize;
+//! use gil::spsc::shm;
+//!
+//! #[derive(Clone, Copy)]
+//! #[repr(C)]
+//! struct Tick {
+//!     price: u64,
+//!     size: u64,
+//! }
+//!
+//! // in the feed handler process
+//! let segment = shm::create::<Tick>("/dev/shm/ticks", NonZeroUsize::new(4096).unwrap())?;
+//! // SAFETY: `Tick` is plain data, and this is the only sender
+//! let mut tx = unsafe { segment.into_sender() };
+//! tx.send(Tick { price: 100, size: 5 });
+//!
+//! // in the strategy process
+//! // SAFETY: the queue was created for `Tick`, and this is the only receiver
+//! let mut rx = unsafe { shm::open::<Tick>("/dev/shm/ticks")?.into_receiver() };
+//! let tick = rx.recv();
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use core::{marker::PhantomData, mem::size_of, num::NonZeroUsize, ptr::NonNull};
+use std::{io, path::Path};
+
+use crate::{
+    alloc,
+    atomic::AtomicUsize,
+    padded::Padded,
+    shm::{Mapping, Preamble, invalid},
+};
+
+pub use self::{receiver::Receiver, sender::Sender};
+
+mod receiver;
+mod sender;
+
+const MAGIC: u64 = u64::from_ne_bytes(*b"gil-spsc");
+
+/// # Invariants
+/// - tail should always point to the place where we can write next to.
+/// - everything but `head` and `tail` is immutable once the preamble is initialised.
+// avoid re-ordering fields, the layout is shared between processes
+#[repr(C)]
+struct Header {
+    preamble: Preamble,
+    size: u64,
+    head: Padded<AtomicUsize>,
+    tail: Padded<AtomicUsize>,
+}
+
+/// A shared-memory mapping holding an SPSC queue of `T`.
+///
+/// Created with [`create`] or [`open`], and turned into the end of the queue used by this process
+/// with [`Segment::into_sender`] or [`Segment::into_receiver`]. The mapping is unmapped when the
+/// handle is dropped, but the backing file is left in place.
+pub struct Segment<T> {
+    map: Mapping,
+    buffer: NonNull<T>,
+    pub(crate) size: usize,
+    pub(crate) mask: usize,
+    _marker: PhantomData<T>,
+}
+
+/// Creates a new file at `path` holding an empty SPSC queue of `T`, and maps it.
+///
+/// The file must not exist yet. It is not removed when the queue is dropped, use
+/// [`std::fs::remove_file`] once no process needs it anymore.
+///
+/// See the [module-level documentation](self) for the requirements on `T`.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file, usually in `/dev/shm` so that it is only backed by memory.
+/// * `capacity` - The capacity of the queue.
+pub fn create<T: Copy + 'static>(
+    path: impl AsRef<Path>,
+    capacity: NonZeroUsize,
+) -> io::Result<Segment<T>> {
+    let size = capacity.get();
+    let capacity = size.next_power_of_two();
+    let (layout, buffer_offset) = Segment::<T>::layout(capacity)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "queue capacity too large"))?;
+
+    let map = Mapping::create(path.as_ref(), layout.size())?;
+    // SAFETY: the mapping is at least `layout.size()` bytes long, and page aligned
+    let segment = unsafe { Segment::from_raw(map, buffer_offset, size, capacity) };
+
+    // SAFETY: the file was just created, so nobody else can have initialised it
+    unsafe {
+        segment.map.as_ptr().cast::<Header>().write(Header {
+            preamble: Preamble::new::<T>(MAGIC, size_of::<Header>(), buffer_offset, capacity),
+            size: size as u64,
+            head: Padded::new(AtomicUsize::new(0)),
+            tail: Padded::new(AtomicUsize::new(0)),
+        });
+    }
+    segment.header().preamble.set_initialised();
+
+    Ok(segment)
+}
+
+/// Maps the SPSC queue of `T` at `path`, which was created with [`create`].
+///
+/// # Safety
+///
+/// The queue must have been created for the same `T`, which must not contain pointers,
+/// references or anything else that is only meaningful inside one process. Only the size and
+/// alignment of `T` are checked.
+///
+/// # Errors
+///
+/// * [`io::ErrorKind::WouldBlock`] if the creator hasn't finished initialising the queue yet.
+/// * [`io::ErrorKind::InvalidData`] if the file doesn't hold a queue of this version of the crate,
+///   or the queue was created for a type with a different size or alignment.
+/// * Any error from opening or mapping the file.
+pub unsafe fn open<T: Copy + 'static>(path: impl AsRef<Path>) -> io::Result<Segment<T>> {
+    let map = Mapping::open(path.as_ref(), size_of::<Header>())?;
+    // SAFETY: the mapping is at least as long as the header, and page aligned
+    let header = unsafe { map.as_ptr().cast::<Header>().as_ref() };
+
+    let (capacity, buffer_offset) = header.preamble.check::<T>(MAGIC, size_of::<Header>())?;
+    let size = header.size as usize;
+    if size == 0 || size > capacity {
+        return Err(invalid("invalid queue capacity"));
+    }
+
+    let (layout, expected_offset) =
+        Segment::<T>::layout(capacity).ok_or_else(|| invalid("invalid queue capacity"))?;
+    if buffer_offset != expected_offset || layout.size() > map.len() {
+        return Err(invalid("queue buffer layout mismatch"));
+    }
+
+    // SAFETY: the mapping holds a valid header and a buffer of `capacity` elements
+    Ok(unsafe { Segment::from_raw(map, buffer_offset, size, capacity) })
+}
+
+impl<T> Segment<T> {
+    /// Turns the mapping into the producer end of the queue.
+    ///
+    /// # Safety
+    ///
+    /// There must be no other [`Sender`] for this queue in any process, and `T` must not contain
+    /// pointers, references or anything else that is only meaningful inside one process.
+    pub unsafe fn into_sender(self) -> Sender<T> {
+        Sender::new(self)
+    }
+
+    /// Turns the mapping into the consumer end of the queue.
+    ///
+    /// # Safety
+    ///
+    /// There must be no other [`Receiver`] for this queue in any process, and `T` must not contain
+    /// pointers, references or anything else that is only meaningful inside one process.
+    pub unsafe fn into_receiver(self) -> Receiver<T> {
+        Receiver::new(self)
+    }
+
+    /// # Safety
+    /// `map` must be long enough to hold the header and a buffer of `capacity` elements at
+    /// `buffer_offset`.
+    unsafe fn from_raw(map: Mapping, buffer_offset: usize, size: usize, capacity: usize) -> Self {
+        Self {
+            buffer: unsafe { map.as_ptr().byte_add(buffer_offset).cast() },
+            map,
+            size,
+            mask: capacity - 1,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns `None` if a buffer of `capacity` elements doesn't fit in the address space.
+    fn layout(capacity: usize) -> Option<(alloc::Layout, usize)> {
+        let header_layout = alloc::Layout::new::<Header>();
+        let buffer_layout = alloc::Layout::array::<T>(capacity).ok()?;
+        let (layout, offset) = header_layout.extend(buffer_layout).ok()?;
+        Some((layout.pad_to_align(), offset))
+    }
+
+    #[inline(always)]
+    fn header(&self) -> &Header {
+        unsafe { self.map.as_ptr().cast::<Header>().as_ref() }
+    }
+
+    #[inline(always)]
+    pub(crate) fn head(&self) -> &AtomicUsize {
+        &self.header().head.value
+    }
+
+    #[inline(always)]
+    pub(crate) fn tail(&self) -> &AtomicUsize {
+        &self.header().tail.value
+    }
+
+    #[inline(always)]
+    pub(crate) unsafe fn get(&self, index: usize) -> T {
+        unsafe { self.buffer.add(index & self.mask).read() }
+    }
+
+    #[inline(always)]
+    pub(crate) unsafe fn set(&self, index: usize, value: T) {
+        unsafe { self.buffer.add(index & self.mask).write(value) }
+    }
+}
+
+unsafe impl<T: Send> Send for Segment<T> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{shm::temp_path, thread};
+
+    #[test]
+    fn test_valid_sends() {
+        const COUNTS: usize = 4096;
+
+        let path = temp_path();
+        let segment = create::<usize>(&path, NonZeroUsize::new(64).unwrap()).unwrap();
+        let mut tx = unsafe { segment.into_sender() };
+        let mut rx = unsafe { open::<usize>(&path).unwrap().into_receiver() };
+        std::fs::remove_file(&path).unwrap();
+
+        thread::spawn(move || {
+            for i in 0..COUNTS << 3 {
+                tx.send(i);
+            }
+        });
+
+        for i in 0..COUNTS << 3 {
+            assert_eq!(rx.recv(), i);
+        }
+    }
+
+    #[test]
+    fn test_valid_try_sends() {
+        let path = temp_path();
+        let segment = create::<usize>(&path, NonZeroUsize::new(4).unwrap()).unwrap();
+        let mut tx = unsafe { segment.into_sender() };
+        let mut rx = unsafe { open::<usize>(&path).unwrap().into_receiver() };
+        std::fs::remove_file(&path).unwrap();
+
+        for _ in 0..4 {
+            assert!(rx.try_recv().is_none());
+        }
+        for i in 0..4 {
+            tx.try_send(i).unwrap();
+        }
+        assert!(tx.try_send(5).is_err());
+
+        for i in 0..4 {
+            assert_eq!(rx.try_recv(), Some(i));
+        }
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_reopen_keeps_position() {
+        let path = temp_path();
+        let segment = create::<u32>(&path, NonZeroUsize::new(8).unwrap()).unwrap();
+        let mut tx = unsafe { segment.into_sender() };
+        for i in 0..3 {
+            tx.send(i);
+        }
+
+        let mut rx = unsafe { open::<u32>(&path).unwrap().into_receiver() };
+        assert_eq!(rx.recv(), 0);
+        drop(rx);
+
+        let mut rx = unsafe { open::<u32>(&path).unwrap().into_receiver() };
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(rx.recv(), 1);
+        assert_eq!(rx.recv(), 2);
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_layout_mismatch() {
+        let path = temp_path();
+        let _segment = create::<u32>(&path, NonZeroUsize::new(8).unwrap()).unwrap();
+
+        let err = unsafe { open::<u64>(&path) }.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = create::<u32>(&path, NonZeroUsize::new(8).unwrap())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        std::fs::remove_file(&path).unwrap();
+
+        std::fs::write(&path, [0u8; 4096]).unwrap();
+        let err = unsafe { open::<u32>(&path) }.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        std::fs::write(&path, [1u8; 4096]).unwrap();
+        let err = unsafe { open::<u32>(&path) }.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_capacity() {
+        let path = temp_path();
+        let segment = create::<u32>(&path, NonZeroUsize::new(8).unwrap()).unwrap();
+
+        // a capacity whose buffer doesn't fit in the address space
+        let capacity = segment.map.as_ptr().cast::<u64>();
+        unsafe { capacity.add(6).write(1 << (usize::BITS - 2)) };
+
+        let err = unsafe { open::<u32>(&path) }.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
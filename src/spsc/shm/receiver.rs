@@ -0,0 +1,107 @@
+use crate::{atomic::Ordering, spsc::shm::Segment};
+
+/// The consumer end of a shared-memory SPSC queue.
+///
+/// This struct is `Send` but not `Sync` or `Clone`. It can be moved to another thread, but cannot be shared
+/// across threads.
+pub struct Receiver<T> {
+    segment: Segment<T>,
+    local_tail: usize,
+    local_head: usize,
+}
+
+impl<T> Receiver<T> {
+    pub(crate) fn new(segment: Segment<T>) -> Self {
+        // the queue may have been used by another process already
+        let local_tail = segment.tail().load(Ordering::Acquire);
+        let local_head = segment.head().load(Ordering::Relaxed);
+        Self {
+            segment,
+            local_tail,
+            local_head,
+        }
+    }
+
+    /// Attempts to receive a value from the queue without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(value)` if a value is available.
+    /// * `None` if the queue is empty.
+    pub fn try_recv(&mut self) -> Option<T> {
+        if self.local_head == self.local_tail {
+            self.load_tail();
+            if self.local_head == self.local_tail {
+                return None;
+            }
+        }
+
+        // SAFETY: head != tail which means queue is not empty and head has valid initialised
+        //         value
+        let ret = unsafe { self.segment.get(self.local_head) };
+        let new_head = self.local_head.wrapping_add(1);
+        self.store_head(new_head);
+        self.local_head = new_head;
+
+        Some(ret)
+    }
+
+    /// Receives a value from the queue, blocking if necessary.
+    ///
+    /// This method uses a spin loop to wait for available data in the queue.
+    /// For a non-blocking alternative, use [`Receiver::try_recv`].
+    pub fn recv(&mut self) -> T {
+        let mut backoff = crate::Backoff::with_spin_count(128);
+        while self.local_head == self.local_tail {
+            backoff.backoff();
+            self.load_tail();
+        }
+
+        // SAFETY: head != tail which means queue is not empty and head has valid initialised
+        //         value
+        let ret = unsafe { self.segment.get(self.local_head) };
+        let new_head = self.local_head.wrapping_add(1);
+        self.store_head(new_head);
+        self.local_head = new_head;
+
+        ret
+    }
+
+    /// Returns an iterator receiving values without blocking, until the queue is empty.
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.try_recv())
+    }
+
+    /// Returns an iterator receiving values, blocking for each one.
+    ///
+    /// Senders in other processes can't be tracked, so the iterator never ends. Use
+    /// [`Receiver::try_iter`] to stop once the queue is empty instead.
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        core::iter::repeat_with(|| self.recv())
+    }
+
+    /// Returns an iterator receiving the values available in the queue when it is called.
+    ///
+    /// Unlike [`Receiver::try_iter`], it ends even if the senders keep sending. Values it didn't
+    /// get to because it was dropped early stay in the queue.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.load_tail();
+        let len = self.local_tail.wrapping_sub(self.local_head);
+        self.try_iter().take(len)
+    }
+
+    /// Drops all the values available in the queue.
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+
+    #[inline(always)]
+    fn store_head(&self, value: usize) {
+        self.segment.head().store(value, Ordering::Release);
+    }
+
+    #[inline(always)]
+    fn load_tail(&mut self) {
+        self.local_tail = self.segment.tail().load(Ordering::Acquire);
+    }
+}
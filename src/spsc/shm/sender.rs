@@ -0,0 +1,80 @@
+use crate::{atomic::Ordering, spsc::shm::Segment};
+
+/// The producer end of a shared-memory SPSC queue.
+///
+/// This struct is `Send` but not `Sync` or `Clone`. It can be moved to another thread, but cannot be shared
+/// across threads.
+pub struct Sender<T> {
+    segment: Segment<T>,
+    local_head: usize,
+    local_tail: usize,
+}
+
+impl<T> Sender<T> {
+    pub(crate) fn new(segment: Segment<T>) -> Self {
+        // the queue may have been used by another process already
+        let local_head = segment.head().load(Ordering::Acquire);
+        let local_tail = segment.tail().load(Ordering::Relaxed);
+        Self {
+            segment,
+            local_head,
+            local_tail,
+        }
+    }
+
+    /// Attempts to send a value into the queue without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the value was successfully sent.
+    /// * `Err(value)` if the queue is full, returning the original value.
+    pub fn try_send(&mut self, value: T) -> Result<(), T> {
+        let new_tail = self.local_tail.wrapping_add(1);
+
+        if new_tail > self.max_tail() {
+            self.load_head();
+            if new_tail > self.max_tail() {
+                return Err(value);
+            }
+        }
+
+        unsafe { self.segment.set(self.local_tail, value) };
+        self.store_tail(new_tail);
+        self.local_tail = new_tail;
+
+        Ok(())
+    }
+
+    /// Sends a value into the queue, blocking if necessary.
+    ///
+    /// This method uses a spin loop to wait for available space in the queue.
+    /// For a non-blocking alternative, use [`Sender::try_send`].
+    pub fn send(&mut self, value: T) {
+        let new_tail = self.local_tail.wrapping_add(1);
+
+        let mut backoff = crate::Backoff::with_spin_count(128);
+        while new_tail > self.max_tail() {
+            backoff.backoff();
+            self.load_head();
+        }
+
+        unsafe { self.segment.set(self.local_tail, value) };
+        self.store_tail(new_tail);
+        self.local_tail = new_tail;
+    }
+
+    #[inline(always)]
+    fn max_tail(&self) -> usize {
+        self.local_head.wrapping_add(self.segment.size)
+    }
+
+    #[inline(always)]
+    fn store_tail(&self, value: usize) {
+        self.segment.tail().store(value, Ordering::Release);
+    }
+
+    #[inline(always)]
+    fn load_head(&mut self) {
+        self.local_head = self.segment.head().load(Ordering::Acquire);
+    }
+}
@@ -0,0 +1,266 @@
+use core::{
+    cell::UnsafeCell, marker::PhantomData, mem::MaybeUninit, num::NonZeroUsize, ptr::NonNull,
+};
+
+use crate::{
+    Dynamic,
+    allocator::NoAlloc,
+    spsc::{
+        self,
+        queue::{Queue, QueuePtr},
+    },
+};
+
+/// A fixed-capacity SPSC queue that never allocates.
+///
+/// The queue header and its buffer of `N` elements are stored inline, so it can live on the stack
+/// or in a `static` on targets without a heap. It is [`split`](StaticQueue::split) into a
+/// [`Producer`] and a [`Consumer`] borrowing it, which use the same algorithm as
+/// [`Sender`](spsc::Sender) and [`Receiver`](spsc::Receiver).
+///
+/// `N` must be a power of two, which is checked at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use gil::spsc::StaticQueue;
+///
+/// let mut queue = StaticQueue::<u32, 4>::new();
+/// let (mut tx, mut rx) = queue.split();
+///
+/// tx.send(1);
+/// assert_eq!(rx.try_recv(), Some(1));
+/// ```
+///
+/// Sharing a queue between an interrupt handler and the main loop:
+///
+/// ```
+/// use gil::spsc::{Consumer, Producer, StaticQueue};
+///
+/// static mut QUEUE: StaticQueue<u8, 64> = StaticQueue::new();
+///
+/// let queue = &raw mut QUEUE;
+/// // SAFETY: split only once, before the interrupt is enabled
+/// let (producer, mut consumer): (Producer<'static, u8>, Consumer<'static, u8>) =
+///     unsafe { (*queue).split() };
+/// ```
+pub struct StaticQueue<T, const N: usize> {
+    queue: UnsafeCell<Queue>,
+    buffer: [UnsafeCell<MaybeUninit<T>>; N],
+}
+
+impl<T, const N: usize> StaticQueue<T, N> {
+    /// Creates a new empty queue.
+    pub const fn new() -> Self {
+        const { assert!(N.is_power_of_two(), "capacity must be a power of two") };
+
+        Self {
+            queue: UnsafeCell::new(Queue::new()),
+            buffer: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
+        }
+    }
+
+    /// Splits the queue into its producer and consumer ends.
+    ///
+    /// The queue keeps its contents once both ends are dropped, and can be split again.
+    pub fn split(&mut self) -> (Producer<'_, T>, Consumer<'_, T>) {
+        let queue = self.queue_ptr();
+
+        (
+            Producer {
+                inner: spsc::Sender::new(queue.clone()),
+                _marker: PhantomData,
+            },
+            Consumer {
+                inner: spsc::Receiver::new(queue),
+                _marker: PhantomData,
+            },
+        )
+    }
+
+    fn queue_ptr(&mut self) -> QueuePtr<T, NoAlloc> {
+        let ptr = NonNull::from(self.queue.get_mut());
+        let buffer = NonNull::from(&mut self.buffer).cast::<T>();
+
+        // SAFETY: the handles borrow the queue, so it outlives them, and the queue itself keeps
+        // the reference it was created with
+        unsafe { QueuePtr::from_static(ptr, buffer, Dynamic::new(NonZeroUsize::new(N).unwrap())) }
+    }
+}
+
+impl<T, const N: usize> Default for StaticQueue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for StaticQueue<T, N> {
+    fn drop(&mut self) {
+        // SAFETY: the handles borrow the queue, so there are none left
+        unsafe { self.queue_ptr().drop_items() };
+    }
+}
+
+// SAFETY: the queue can only be accessed through the handles, which need a unique reference to it
+unsafe impl<T: Send, const N: usize> Send for StaticQueue<T, N> {}
+unsafe impl<T: Send, const N: usize> Sync for StaticQueue<T, N> {}
+
+/// The producer end of a [`StaticQueue`].
+///
+/// This struct is `Send` but not `Sync` or `Clone`. It can be moved to another thread or
+/// interrupt context, but cannot be shared.
+pub struct Producer<'a, T> {
+    inner: spsc::Sender<T, NoAlloc>,
+    _marker: PhantomData<&'a mut ()>,
+}
+
+impl<T> Producer<'_, T> {
+    /// Attempts to send a value into the queue without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the value was successfully sent.
+    /// * `Err(value)` if the queue is full or closed, returning the original value.
+    pub fn try_send(&mut self, value: T) -> Result<(), T> {
+        self.inner.try_send(value)
+    }
+
+    /// Sends a value into the queue, blocking if necessary.
+    ///
+    /// This method uses a spin loop to wait for available space in the queue.
+    /// For a non-blocking alternative, use [`Producer::try_send`]. If the queue is closed, the
+    /// value is dropped.
+    pub fn send(&mut self, value: T) {
+        self.inner.send(value)
+    }
+
+    /// Returns a mutable slice to the available write buffer in the queue.
+    ///
+    /// See [`Sender::write_buffer`](spsc::Sender::write_buffer).
+    pub fn write_buffer(&mut self) -> &mut [MaybeUninit<T>] {
+        self.inner.write_buffer()
+    }
+
+    /// Commits items written to the buffer obtained via [`Producer::write_buffer`].
+    ///
+    /// # Safety
+    ///
+    /// See [`Sender::commit`](spsc::Sender::commit).
+    pub unsafe fn commit(&mut self, len: usize) {
+        unsafe { self.inner.commit(len) }
+    }
+
+    /// Returns `true` if the consumer closed the queue or was dropped.
+    ///
+    /// See [`Sender::is_closed`](spsc::Sender::is_closed).
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+}
+
+/// The consumer end of a [`StaticQueue`].
+///
+/// This struct is `Send` but not `Sync` or `Clone`. It can be moved to another thread or
+/// interrupt context, but cannot be shared.
+pub struct Consumer<'a, T> {
+    inner: spsc::Receiver<T, NoAlloc>,
+    _marker: PhantomData<&'a mut ()>,
+}
+
+impl<T> Consumer<'_, T> {
+    /// Attempts to receive a value from the queue without blocking.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(value)` if a value is available.
+    /// * `None` if the queue is empty.
+    pub fn try_recv(&mut self) -> Option<T> {
+        self.inner.try_recv()
+    }
+
+    /// Returns a reference to the next value in the queue without receiving it.
+    ///
+    /// See [`Receiver::peek`](spsc::Receiver::peek).
+    pub fn peek(&self) -> Option<&T> {
+        self.inner.peek()
+    }
+
+    /// Receives the next value in the queue if `f` returns `true` for it, without blocking.
+    ///
+    /// See [`Receiver::recv_if`](spsc::Receiver::recv_if).
+    pub fn recv_if(&mut self, f: impl FnOnce(&T) -> bool) -> Option<T> {
+        self.inner.recv_if(f)
+    }
+
+    /// Receives a value from the queue, blocking if necessary.
+    ///
+    /// This method uses a spin loop to wait for available data in the queue.
+    /// For a non-blocking alternative, use [`Consumer::try_recv`].
+    pub fn recv(&mut self) -> T {
+        self.inner.recv()
+    }
+
+    /// Returns an iterator receiving values without blocking, until the queue is empty.
+    pub fn try_iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.inner.try_iter()
+    }
+
+    /// Returns an iterator receiving values, blocking for each one.
+    ///
+    /// See [`Receiver::iter`](spsc::Receiver::iter).
+    pub fn iter(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.inner.iter()
+    }
+
+    /// Returns an iterator receiving the values available in the queue when it is called.
+    ///
+    /// See [`Receiver::drain`](spsc::Receiver::drain).
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.inner.drain()
+    }
+
+    /// Drops all the values available in the queue.
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+
+    /// Returns a slice to the available read buffer in the queue.
+    ///
+    /// See [`Receiver::read_buffer`](spsc::Receiver::read_buffer).
+    pub fn read_buffer(&mut self) -> &[T] {
+        self.inner.read_buffer()
+    }
+
+    /// Advances the consumer head by `len` items read via [`Consumer::read_buffer`], without
+    /// dropping them.
+    ///
+    /// # Safety
+    ///
+    /// See [`Receiver::advance`](spsc::Receiver::advance).
+    pub unsafe fn advance(&mut self, len: usize) {
+        unsafe { self.inner.advance(len) }
+    }
+
+    /// Advances the consumer head by `len` items read via [`Consumer::read_buffer`], dropping them.
+    ///
+    /// # Safety
+    ///
+    /// See [`Receiver::discard`](spsc::Receiver::discard).
+    pub unsafe fn discard(&mut self, len: usize) {
+        unsafe { self.inner.discard(len) }
+    }
+
+    /// Closes the queue, so that sending fails until it is split again.
+    ///
+    /// See [`Receiver::close`](spsc::Receiver::close).
+    pub fn close(&mut self) {
+        self.inner.close()
+    }
+
+    /// Returns `true` if the producer was dropped, or if the queue was closed.
+    ///
+    /// See [`Receiver::is_closed`](spsc::Receiver::is_closed).
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+}